@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::quest::{NoDeathQuest, Quest, RawQuest, SignQuest};
+use crate::tileset::TileId;
+use crate::utils::{Event, Position};
+
+/// what earns an achievement, checked like a quest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Goal {
+    /// any quest of map files, e.g. `Step("water", 9)` to swim 9 tiles without surfacing
+    Quest(RawQuest),
+    ReadAllSigns,
+    /// win the map without dying
+    NoDeath,
+}
+
+/// an achievement in the achievements file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawAchievement {
+    pub name: String,
+    pub description: String,
+    pub goal: Goal,
+}
+
+impl RawAchievement {
+    /// ids of the tiles the goal names
+    pub fn tiles(&self) -> Vec<&TileId> {
+        match &self.goal {
+            Goal::Quest(raw_quest) => raw_quest.tiles(),
+            _ => vec![],
+        }
+    }
+
+    /// the goal as a quest, on a map with signs at the positions
+    pub fn quest(&self, signs: &HashSet<Position>) -> Box<dyn Quest<Event>> {
+        match &self.goal {
+            Goal::Quest(raw_quest) => raw_quest.into(),
+            Goal::ReadAllSigns => Box::new(SignQuest::new(signs.clone())),
+            Goal::NoDeath => Box::new(NoDeathQuest::new()),
+        }
+    }
+}
+
+pub fn read_achievements<P: AsRef<Path>>(path: P) -> Result<Vec<RawAchievement>, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    Ok(ron::from_str::<Vec<RawAchievement>>(&content)?)
+}
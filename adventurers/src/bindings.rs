@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use termgame::KeyCode;
+
+use crate::movement::MovementRule;
+use crate::player::PLAYER_SPEED;
+
+/// help lists this many bindings in a line
+const HELP_COLUMNS: usize = 3;
+const HELP_COLUMN_WIDTH: usize = 26;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveUpLeft,
+    MoveUpRight,
+    MoveDownLeft,
+    MoveDownRight,
+    ToggleBag,
+    ToggleQuest,
+    Debug,
+    Undo,
+    Target,
+    Help,
+    Pause,
+    Timer,
+    Log,
+    Map,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUpLeft,
+        Action::MoveUpRight,
+        Action::MoveDownLeft,
+        Action::MoveDownRight,
+        Action::ToggleBag,
+        Action::ToggleQuest,
+        Action::Debug,
+        Action::Undo,
+        Action::Target,
+        Action::Help,
+        Action::Pause,
+        Action::Timer,
+        Action::Log,
+        Action::Map,
+    ];
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Action::MoveUp => "up",
+            Action::MoveDown => "down",
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::MoveUpLeft => "up left",
+            Action::MoveUpRight => "up right",
+            Action::MoveDownLeft => "down left",
+            Action::MoveDownRight => "down right",
+            Action::ToggleBag => "bag",
+            Action::ToggleQuest => "quest",
+            Action::Debug => "debug",
+            Action::Undo => "undo push",
+            Action::Target => "walk to",
+            Action::Help => "help",
+            Action::Pause => "pause",
+            Action::Timer => "timer",
+            Action::Log => "message log",
+            Action::Map => "map",
+        })
+    }
+}
+
+/// keys that can be bound, KeyCode is not serializable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+    Char(char),
+}
+
+impl Key {
+    pub fn from_key_code(key_code: KeyCode) -> Option<Key> {
+        Some(match key_code {
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Enter => Key::Enter,
+            KeyCode::Tab => Key::Tab,
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Esc => Key::Esc,
+            KeyCode::Char(c) => Key::Char(c),
+            _ => return None,
+        })
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Up => write!(f, "↑"),
+            Key::Down => write!(f, "↓"),
+            Key::Left => write!(f, "←"),
+            Key::Right => write!(f, "→"),
+            Key::Home => write!(f, "Home"),
+            Key::End => write!(f, "End"),
+            Key::PageUp => write!(f, "PageUp"),
+            Key::PageDown => write!(f, "PageDown"),
+            Key::Enter => write!(f, "Enter"),
+            Key::Tab => write!(f, "Tab"),
+            Key::Backspace => write!(f, "Backspace"),
+            Key::Esc => write!(f, "Esc"),
+            Key::Char(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    /// arrow keys only
+    #[default]
+    Arrows,
+    /// arrow keys and wasd
+    Wasd,
+    /// arrow keys and hjkl
+    Vi,
+}
+
+fn default_speed() -> f32 {
+    PLAYER_SPEED
+}
+
+/// content of a bindings file, keys override the preset
+#[derive(Serialize, Deserialize)]
+pub struct RawBindings {
+    #[serde(default)]
+    pub preset: Preset,
+    #[serde(default)]
+    pub keys: HashMap<Key, Action>,
+    /// movement speed in tiles per second
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    #[serde(default)]
+    pub movement: MovementRule,
+}
+
+impl Default for RawBindings {
+    fn default() -> Self {
+        RawBindings {
+            preset: Default::default(),
+            keys: Default::default(),
+            speed: PLAYER_SPEED,
+            movement: Default::default(),
+        }
+    }
+}
+
+pub struct Bindings {
+    keys: HashMap<Key, Action>,
+    pub speed: f32,
+    pub movement: MovementRule,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::from(&RawBindings::default())
+    }
+}
+
+impl From<&RawBindings> for Bindings {
+    fn from(raw_bindings: &RawBindings) -> Self {
+        use Action::*;
+        let mut keys = HashMap::from([
+            (Key::Up, MoveUp),
+            (Key::Down, MoveDown),
+            (Key::Left, MoveLeft),
+            (Key::Right, MoveRight),
+            // where the numpad sends them
+            (Key::Home, MoveUpLeft),
+            (Key::PageUp, MoveUpRight),
+            (Key::End, MoveDownLeft),
+            (Key::PageDown, MoveDownRight),
+            (Key::Char('b'), ToggleBag),
+            (Key::Char('q'), ToggleQuest),
+            (Key::Char('t'), Debug),
+            (Key::Char('u'), Undo),
+            (Key::Char('g'), Target),
+            (Key::Char('?'), Help),
+            (Key::Esc, Pause),
+            (Key::Char('p'), Pause),
+            (Key::Char('r'), Timer),
+            (Key::Char('o'), Log),
+            (Key::Char('m'), Map),
+        ]);
+        let movement = match raw_bindings.preset {
+            Preset::Arrows => "",
+            Preset::Wasd => "wsad",
+            Preset::Vi => "kjhl",
+        };
+        for (c, action) in movement.chars().zip([MoveUp, MoveDown, MoveLeft, MoveRight]) {
+            keys.insert(Key::Char(c), action);
+        }
+        keys.extend(raw_bindings.keys.iter());
+        Bindings {
+            keys,
+            speed: raw_bindings.speed,
+            movement: raw_bindings.movement,
+        }
+    }
+}
+
+impl Bindings {
+    pub fn action(&self, key_code: KeyCode) -> Option<Action> {
+        self.keys.get(&Key::from_key_code(key_code)?).cloned()
+    }
+
+    /// every action with the keys bound to it
+    pub fn help(&self) -> String {
+        let entries = Action::ALL.iter().map(|action| {
+            let mut keys = self
+                .keys
+                .iter()
+                .filter(|(_, a)| *a == action)
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>();
+            keys.sort();
+            let keys = keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+            format!("{}: {}", action, keys.join(" "))
+        });
+        let entries = entries
+            .map(|entry| format!("{:width$}", entry, width = HELP_COLUMN_WIDTH))
+            .collect::<Vec<_>>();
+        let mut lines = entries
+            .chunks(HELP_COLUMNS)
+            .map(|line| line.concat().trim_end().to_string())
+            .collect::<Vec<_>>();
+        lines.push("Ctrl + c: quit".into());
+        lines.join("\n")
+    }
+}
+
+pub fn read_bindings<P: AsRef<Path>>(path: P) -> Result<Bindings, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    let raw_bindings = ron::from_str::<RawBindings>(&content)?;
+    Ok(Bindings::from(&raw_bindings))
+}
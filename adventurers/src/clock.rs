@@ -0,0 +1,47 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// ticks of a whole day and night
+const DAY_TICKS: i32 = 2400;
+/// ticks of daylight from the start of a day, the rest is night
+const DAYLIGHT_TICKS: i32 = 1600;
+/// hour of the clock a game starts at
+const MORNING: i32 = 6;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeOfDay {
+    #[default]
+    Day,
+    Night,
+}
+
+impl TimeOfDay {
+    /// time of day at the tick
+    pub fn at(frame: i32) -> Self {
+        match frame.rem_euclid(DAY_TICKS) < DAYLIGHT_TICKS {
+            true => TimeOfDay::Day,
+            false => TimeOfDay::Night,
+        }
+    }
+
+    /// flag set while it is this time, e.g. to require in dialogue choices
+    pub fn flag(&self) -> &'static str {
+        match self {
+            TimeOfDay::Day => "day",
+            TimeOfDay::Night => "night",
+        }
+    }
+}
+
+impl Display for TimeOfDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.flag())
+    }
+}
+
+/// hours and minutes on the clock at the tick
+pub fn clock_text(frame: i32) -> String {
+    let minutes = frame.rem_euclid(DAY_TICKS) * 24 * 60 / DAY_TICKS + MORNING * 60;
+    format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::map::MapLayers;
+use crate::path::find_path;
+use crate::plain::ENEMY_ASCII;
+use crate::utils::{Item, Position};
+
+fn default_speed() -> i32 {
+    5
+}
+
+fn default_sight() -> i32 {
+    5
+}
+
+fn default_damage() -> i32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Enemy {
+    pub name: String,
+    pub icon: char,
+    /// drawn instead of the icon without unicode
+    #[serde(default)]
+    pub ascii: Option<char>,
+    pub position: Position,
+    /// ticks between two steps
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    /// chase the player within the distance
+    #[serde(default = "default_sight")]
+    pub sight: i32,
+    /// walk along the positions in turn when not chasing
+    #[serde(default)]
+    pub patrol: Vec<Position>,
+    /// enemies avoid water unless they can swim
+    #[serde(default)]
+    pub swim: bool,
+    #[serde(default = "default_damage")]
+    pub damage: i32,
+    /// player defeats the enemy by walking into it with the item in bag
+    #[serde(default)]
+    pub weakness: Option<Item>,
+    #[serde(default)]
+    pub chasing: bool,
+    #[serde(default)]
+    pub waypoint: usize,
+}
+
+impl Enemy {
+    /// icon drawn without unicode
+    pub fn ascii_icon(&self) -> char {
+        match self.ascii {
+            Some(ascii) => ascii,
+            None if self.icon.is_ascii() => self.icon,
+            None => ENEMY_ASCII,
+        }
+    }
+
+    pub fn sees(&self, position: &Position) -> bool {
+        (self.position.0 - position.0).abs() <= self.sight
+            && (self.position.1 - position.1).abs() <= self.sight
+    }
+
+    pub fn can_walk(&self, map_layers: &MapLayers, position: &Position) -> bool {
+        map_layers.is_free(position) && (self.swim || !map_layers.is_water(position))
+    }
+
+    /// next position to step onto, chasing the player if in sight
+    pub fn next_step(&mut self, map_layers: &MapLayers, player: &Position) -> Option<Position> {
+        let target = if self.chasing {
+            *player
+        } else {
+            let waypoint = *self.patrol.get(self.waypoint)?;
+            if waypoint == self.position {
+                self.waypoint = (self.waypoint + 1) % self.patrol.len();
+            }
+            self.patrol[self.waypoint]
+        };
+        let path = find_path(self.position, target, |p| self.can_walk(map_layers, p))?;
+        path.first().cloned()
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::Item;
+
+fn default_swim() -> f32 {
+    1.0
+}
+
+/// where a piece of equipment is worn, one piece each
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Slot {
+    Head,
+    Body,
+    Feet,
+}
+
+/// an item put on once picked up, listed in the map file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Equipment {
+    pub item: Item,
+    pub name: String,
+    pub slot: Slot,
+    /// added to the oxygen the player holds at most
+    #[serde(default)]
+    pub oxygen: i32,
+    /// times the cost of moving through water, e.g. 0.5 for flippers
+    #[serde(default = "default_swim")]
+    pub swim: f32,
+}
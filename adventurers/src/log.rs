@@ -0,0 +1,50 @@
+use crate::npc::wrap;
+
+/// width of a log line, without the tick in front
+const LINE_WIDTH: usize = 66;
+/// lines of the log shown at once
+const PAGE_LINES: usize = 8;
+
+/// everything that happened, newest last
+#[derive(Default)]
+pub struct MessageLog {
+    lines: Vec<String>,
+    /// lines scrolled back from the newest
+    scroll: usize,
+}
+
+impl MessageLog {
+    /// add the text at the tick of the run
+    pub fn push(&mut self, tick: i32, text: &str) {
+        for (i, line) in wrap(text, LINE_WIDTH).into_iter().enumerate() {
+            let tick = if i == 0 { format!("[{tick}]") } else { String::new() };
+            self.lines.push(format!("{tick:>10} {line}"));
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        let max = self.lines.len().saturating_sub(PAGE_LINES);
+        self.scroll = (self.scroll + 1).min(max);
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    /// back to the newest lines
+    pub fn reset_scroll(&mut self) {
+        self.scroll = 0;
+    }
+
+    /// a page of lines up to the scrolled one
+    pub fn text(&self) -> String {
+        if self.lines.is_empty() {
+            return "Nothing yet".into();
+        }
+        let end = self.lines.len() - self.scroll;
+        let start = end.saturating_sub(PAGE_LINES);
+        let mut lines = self.lines[start..end].to_vec();
+        lines.push(format!("{}-{} of {}, up/down to scroll", start + 1, end, self.lines.len()));
+        lines.join("\n")
+    }
+}
@@ -1,108 +1,1225 @@
+use std::collections::HashSet;
+use std::env;
 use std::error::Error;
+use std::io::stdout;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use termgame::{run_game, Controller, Game, GameEvent, GameSettings, KeyCode, SimpleEvent};
+use crossterm::event::{
+    KeyboardEnhancementFlags, MouseEventKind, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
+use crossterm::execute;
+use termgame::{
+    run_game, CharChunkMap, Controller, Game, GameEvent, GameSettings, KeyCode, KeyEvent,
+    KeyEventKind, MouseEvent, SimpleEvent, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+mod bindings;
+use bindings::{read_bindings, Action, Bindings};
 
 mod utils;
-use utils::{Control, Event, ForegroundVariant, MessageType, Position, BackgroundVariant};
+use utils::{pseudo_random, Control, Event, ForegroundVariant, Input, MessageType, Position};
 
 mod map;
-use map::{read_map_data, MapLayers, RawGameMap};
+use map::{read_map_data, BoulderPush, MapLayers, RawGameMap};
+
+mod path;
+use path::find_safe_path;
+
+mod enemy;
+use enemy::Enemy;
+
+mod npc;
+use npc::{Dialogue, DialogueEffect, Npc};
+
+mod movement;
+
+mod menu;
+use menu::{
+    next_speed, options_menu, pause_menu, title_menu, Menu, OptionItem, PauseItem, TitleItem,
+};
 
 mod player;
 use player::Player;
 
+mod equipment;
+
+mod trigger;
+use trigger::Trigger;
+
 mod quest;
-use quest::{Quest, StepQuest, PickupQuest, CompoundQuest};
+use quest::{default_quests, Quest, QuestStatus, RawQuest, WinCondition};
+
+mod save;
+use save::{read_save, replay_quests, write_save, SaveGame};
+
+mod stats;
+use stats::Statistics;
+
+mod records;
+use records::{read_records, write_records, Records};
+
+mod timer;
+use timer::{inputs_path, read_splits, splits_path, write_inputs, write_splits, Split, Timer};
+
+mod achievement;
+use achievement::{read_achievements, RawAchievement};
+
+mod log;
+use log::MessageLog;
+
+mod notify;
+use notify::Notifications;
+
+mod minimap;
+use minimap::{render_minimap, MapView};
+
+mod clock;
+use clock::{clock_text, TimeOfDay};
+
+mod tide;
+
+mod tileset;
+use tileset::{read_tileset, Tile, Tileset};
+
+mod theme;
+use theme::{builtin_themes, read_themes, Theme};
+
+mod plain;
+use plain::RenderMode;
 
 /// if distance between player and border < padding, move viewport
 const VIEW_PADDING: i32 = 2;
+const TICK_MILLIS: u64 = 50;
+/// tiles slid or drifted at most in a tick
+const MAX_SLIDE: usize = 64;
+/// npcs take a wandering step every so many ticks
+const NPC_WANDER_TICKS: i32 = 10;
+const MAPS_DIR: &str = "../maps";
+const RECORDS_PATH: &str = "../records.ron";
+const ACHIEVEMENTS_PATH: &str = "../achievements.ron";
+const TILESET_PATH: &str = "../tileset.ron";
+const THEMES_PATH: &str = "../themes.ron";
+const TITLE: &str = "Adventurers";
 
 #[derive(Default)]
 enum GameStatus {
+    /// picking a map to play
+    Title(Menu<TitleItem>),
     #[default]
     Running,
+    Paused(Menu<PauseItem>),
+    Options(Menu<OptionItem>),
+    /// best results and lifetime totals, on the page
+    Records(usize),
     Died,
+    /// the win conditions of the map hold
+    Victory,
 }
 
-#[derive(Default)]
-struct GameVar {
-    game_status: GameStatus,
-    control: Control,
-    viewport_position: Position,
-    message: MessageType,
-    frame: i32,
-    player: Player,
-    map_layers: MapLayers,
-    events: Vec<Event>,
-    quests: Vec<Box<dyn Quest<Event>>>,
-}
+#[derive(Default)]
+struct GameVar {
+    game_status: GameStatus,
+    control: Control,
+    viewport_position: Position,
+    notifications: Notifications,
+    frame: i32,
+    player: Player,
+    map_layers: MapLayers,
+    events: Vec<Event>,
+    /// boulder pushes to undo, the latest last
+    pushes: Vec<BoulderPush>,
+    triggers: Vec<Trigger>,
+    npcs: Vec<Npc>,
+    enemies: Vec<Enemy>,
+    dialogue: Option<Dialogue>,
+    /// target being picked to walk to
+    cursor: Option<Position>,
+    /// steps left to auto walk, the next step at the end
+    auto_path: Vec<Position>,
+    /// step the player took this tick, kept on sliding over ice
+    moved: Option<Position>,
+    flags: HashSet<String>,
+    quests: Vec<Box<dyn Quest<Event>>>,
+    /// quests with the number of events in history when they started
+    raw_quests: Vec<(RawQuest, usize)>,
+    /// every event handed to quests with its tick of the run, replayed to load a saved game
+    history: Vec<(i32, Event)>,
+    /// kept over deaths, shown on victory
+    statistics: Statistics,
+    /// inputs of every tick of the run, kept over deaths to simulate the run again
+    inputs: Vec<(i32, Input)>,
+    timer: Timer,
+    /// goals of the achievements, in the order of the achievements file
+    achievements: Vec<Box<dyn Quest<Event>>>,
+    log: MessageLog,
+    /// log is shown and scrolled instead of moving
+    reading_log: bool,
+    map_view: MapView,
+    /// tiles covered by the map overlay, drawn again once it moves or hides
+    overlay: Vec<Position>,
+}
+
+struct GameStatic {
+    map_path: PathBuf,
+    raw_game_map: RawGameMap,
+    bindings: Bindings,
+    records: Records,
+    raw_achievements: Vec<RawAchievement>,
+    /// tiles of every map, maps add their own
+    tileset: Tileset,
+    themes: Vec<Theme>,
+    /// index of the theme in use
+    theme: usize,
+    render: RenderMode,
+    screen_size: (u16, (u16, u16)),
+}
+
+struct MyGame {
+    game_var: GameVar,
+    game_static: GameStatic,
+}
+
+impl MyGame {
+    fn new(
+        bindings: Bindings,
+        records: Records,
+        raw_achievements: Vec<RawAchievement>,
+        tileset: Tileset,
+        themes: Vec<Theme>,
+        render: RenderMode,
+    ) -> Self {
+        let game_static = GameStatic {
+            map_path: Default::default(),
+            raw_game_map: Default::default(),
+            bindings,
+            records,
+            raw_achievements,
+            tileset,
+            themes,
+            theme: 0,
+            render,
+            screen_size: Default::default(),
+        };
+        Self {
+            game_var: Default::default(),
+            game_static,
+        }
+    }
+
+    fn init(&mut self, game: &Game) {
+        let raw_quests = match self.game_static.raw_game_map.quests.is_empty() {
+            true => default_quests(),
+            false => self.game_static.raw_game_map.quests.clone(),
+        };
+
+        self.game_static.screen_size = game.screen_size();
+        let player = Player {
+            speed: self.game_static.bindings.speed,
+            ..Default::default()
+        };
+        let map_layers = MapLayers::from(&self.game_static.raw_game_map);
+        self.game_var = GameVar {
+            player,
+            achievements: self.achievement_quests(&map_layers),
+            map_layers,
+            quests: raw_quests.iter().map(|q| q.into()).collect(),
+            raw_quests: raw_quests.into_iter().map(|q| (q, 0)).collect(),
+            timer: Timer {
+                best: self.best_splits(),
+                ..Default::default()
+            },
+            triggers: self.game_static.raw_game_map.triggers.clone(),
+            npcs: self.game_static.raw_game_map.npcs.clone(),
+            enemies: self.game_static.raw_game_map.enemies.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn achievement_quests(&self, map_layers: &MapLayers) -> Vec<Box<dyn Quest<Event>>> {
+        let signs = map_layers.signs();
+        let raw_achievements = self.game_static.raw_achievements.iter();
+        raw_achievements.map(|a| a.quest(&signs)).collect()
+    }
+
+    /// hand the event to achievements not yet unlocked, return names of the ones it unlocks
+    fn update_achievements(&mut self, event: &Event) -> Vec<String> {
+        let GameStatic {
+            ref raw_achievements,
+            ref mut records,
+            ..
+        } = self.game_static;
+
+        let mut unlocked = vec![];
+        for (quest, raw) in self.game_var.achievements.iter_mut().zip(raw_achievements) {
+            if records.achievements.contains(&raw.name) {
+                continue;
+            }
+            quest.update(event);
+            if quest.is_completed() {
+                records.achievements.insert(raw.name.clone());
+                unlocked.push(raw.name.clone());
+            }
+        }
+        if !unlocked.is_empty() {
+            self.save_records();
+        }
+        unlocked
+    }
+
+    /// draw the map in the colours of the theme in use, as plainly as the terminal needs
+    fn apply_theme(&mut self) {
+        let GameStatic {
+            ref raw_game_map,
+            ref themes,
+            theme,
+            render,
+            ..
+        } = self.game_static;
+        self.game_var.map_layers.render = render;
+        self.game_var.map_layers.ascii_icons = raw_game_map.ascii_icons();
+        self.game_var.map_layers.set_theme(&raw_game_map.tileset, &themes[theme]);
+    }
+
+    /// splits of the personal best run on the map, if any
+    fn best_splits(&self) -> Vec<Split> {
+        read_splits(splits_path(&self.game_static.map_path)).unwrap_or_default()
+    }
+
+    fn load_map(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut raw_game_map = read_map_data(path)?;
+        raw_game_map.tileset = self.game_static.tileset.with(&raw_game_map.tileset);
+        if let Some(id) = raw_game_map.unknown_tile() {
+            return Err(format!("no tile '{id}' in the tileset").into());
+        }
+        for achievement in self.game_static.raw_achievements.iter() {
+            let tiles = achievement.tiles();
+            if let Some(id) = tiles.iter().find(|id| !raw_game_map.tileset.contains(id)) {
+                return Err(format!("no tile '{id}' for achievement {}", achievement.name).into());
+            }
+        }
+        if let Some(error) = raw_game_map.npcs.iter().find_map(|npc| npc.dialogue_error()) {
+            return Err(error.into());
+        }
+        if raw_game_map.win.is_empty() {
+            return Err("no win conditions, the map could never be won".into());
+        }
+        self.game_static.raw_game_map = raw_game_map;
+        self.game_static.map_path = path.to_path_buf();
+        Ok(())
+    }
+
+    /// start the loaded map from the beginning
+    fn new_game(&mut self, game: &mut Game) {
+        game.swap_chunkmap(&mut CharChunkMap::new());
+        self.init(game);
+        self.apply_theme();
+
+        let GameVar {
+            ref mut player,
+            ref mut map_layers,
+            ..
+        } = self.game_var;
+        player.move_to(self.game_static.raw_game_map.start);
+
+        map_layers.update_player(player);
+    }
+
+    /// start the map again after death, keeping what counts over the whole run
+    fn restart(&mut self, game: &mut Game) {
+        let GameVar {
+            statistics,
+            history,
+            achievements,
+            log,
+            inputs,
+            ..
+        } = std::mem::take(&mut self.game_var);
+        self.new_game(game);
+
+        // quests start anew, the history goes on
+        for (_, start) in self.game_var.raw_quests.iter_mut() {
+            *start = history.len();
+        }
+        self.game_var.statistics = statistics;
+        self.game_var.history = history;
+        self.game_var.achievements = achievements;
+        self.game_var.log = log;
+        self.game_var.inputs = inputs;
+        self.record(Input::Restart);
+    }
+
+    /// keep what the player did on this tick of the run
+    fn record(&mut self, input: Input) {
+        let tick = self.game_var.statistics.ticks;
+        self.game_var.inputs.push((tick, input));
+    }
+
+    fn continue_game(&mut self, game: &mut Game, path: &Path) -> Result<(), Box<dyn Error>> {
+        let save = read_save(path)?;
+        self.load_map(&save.map)?;
+        game.swap_chunkmap(&mut CharChunkMap::new());
+        self.game_static.screen_size = game.screen_size();
+
+        let mut player = save.player;
+        player.speed = self.game_static.bindings.speed;
+        player.update_draw = true;
+        let map_layers = save.map_layers;
+        let mut timer = Timer {
+            best: self.best_splits(),
+            ..Default::default()
+        };
+        let mut achievements = self.achievement_quests(&map_layers);
+        for (_, event) in save.history.iter() {
+            achievements.iter_mut().for_each(|quest| quest.update(event));
+        }
+        self.game_var = GameVar {
+            achievements,
+            frame: save.frame,
+            viewport_position: save.viewport_position,
+            player,
+            map_layers,
+            npcs: save.npcs,
+            enemies: save.enemies,
+            triggers: save.triggers,
+            flags: save.flags,
+            quests: replay_quests(&save.quests, &save.history, &mut timer),
+            timer,
+            raw_quests: save.quests,
+            history: save.history,
+            events: save.events,
+            statistics: save.statistics,
+            inputs: save.inputs,
+            ..Default::default()
+        };
+        self.apply_theme();
+        Ok(())
+    }
+
+    fn save_game(&self) -> Result<(), Box<dyn Error>> {
+        let GameVar {
+            frame,
+            viewport_position,
+            ref player,
+            ref map_layers,
+            ref npcs,
+            ref enemies,
+            ref triggers,
+            ref flags,
+            ref raw_quests,
+            ref history,
+            ref events,
+            ref statistics,
+            ref inputs,
+            ..
+        } = self.game_var;
+
+        write_save(&SaveGame {
+            map: self.game_static.map_path.clone(),
+            frame,
+            viewport_position,
+            player: player.clone(),
+            map_layers: map_layers.clone(),
+            npcs: npcs.clone(),
+            enemies: enemies.clone(),
+            triggers: triggers.clone(),
+            flags: flags.clone(),
+            quests: raw_quests.clone(),
+            history: history.clone(),
+            events: events.clone(),
+            statistics: statistics.clone(),
+            inputs: inputs.clone(),
+        })
+    }
+
+    /// leave the map and pick another one, showing the title
+    fn show_title(&mut self, game: &mut Game, title: String) {
+        game.swap_chunkmap(&mut CharChunkMap::new());
+        self.game_var = GameVar {
+            game_status: GameStatus::Title(title_menu(MAPS_DIR, title)),
+            ..Default::default()
+        };
+        self.show_menu();
+    }
+
+    fn show_menu(&mut self) {
+        let GameVar {
+            ref game_status,
+            ref mut notifications,
+            ..
+        } = self.game_var;
+
+        notifications.push(match game_status {
+            GameStatus::Title(menu) => menu.into(),
+            GameStatus::Paused(menu) => menu.into(),
+            GameStatus::Options(menu) => menu.into(),
+            _ => return,
+        });
+    }
+
+    fn on_title_event(&mut self, game: &mut Game, key_code: KeyCode) {
+        let action = self.game_static.bindings.action(key_code);
+        let item = match self.game_var.game_status {
+            GameStatus::Title(ref mut menu) => menu.on_key(key_code, action),
+            _ => return,
+        };
+        self.show_menu();
+
+        let result = match item {
+            Some(TitleItem::New(path)) => self.load_map(&path).map(|_| self.new_game(game)),
+            Some(TitleItem::Continue(path)) => self.continue_game(game, &path),
+            Some(TitleItem::Records) => {
+                let text = self.game_static.records.text(0);
+                self.game_var.notifications.push(MessageType::Menu("Records".into(), text));
+                self.game_var.game_status = GameStatus::Records(0);
+                Ok(())
+            }
+            Some(TitleItem::Quit) => {
+                quit(game);
+                Ok(())
+            }
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            self.show_title(game, format!("Cannot load the game: {e}"));
+        }
+    }
+
+    fn on_pause_event(&mut self, game: &mut Game, key_code: KeyCode) {
+        let action = self.game_static.bindings.action(key_code);
+        let item = match (&mut self.game_var.game_status, action) {
+            (GameStatus::Paused(_), Some(Action::Pause)) => Some(PauseItem::Resume),
+            (GameStatus::Paused(menu), _) => menu.on_key(key_code, action),
+            _ => return,
+        };
+
+        match item {
+            Some(PauseItem::Resume) => {
+                self.game_var.game_status = GameStatus::Running;
+                self.game_var.notifications.clear_modal();
+                return;
+            }
+            Some(PauseItem::Save) => {
+                let title = match self.save_game() {
+                    Ok(()) => "Game saved".into(),
+                    Err(e) => format!("Cannot save: {e}"),
+                };
+                if let GameStatus::Paused(ref mut menu) = self.game_var.game_status {
+                    menu.title = title;
+                }
+            }
+            Some(PauseItem::Options) => {
+                let GameStatic {
+                    ref bindings,
+                    ref themes,
+                    theme,
+                    ..
+                } = self.game_static;
+                let menu = options_menu(bindings, &themes[theme].name);
+                self.game_var.game_status = GameStatus::Options(menu);
+            }
+            Some(PauseItem::Title) => {
+                self.show_title(game, TITLE.into());
+                return;
+            }
+            Some(PauseItem::Quit) => quit(game),
+            None => {}
+        }
+        self.show_menu();
+    }
+
+    fn on_options_event(&mut self, key_code: KeyCode) {
+        let GameStatic {
+            ref mut bindings, ..
+        } = self.game_static;
+        let action = bindings.action(key_code);
+        let (item, selected) = match (&mut self.game_var.game_status, action) {
+            (GameStatus::Options(menu), Some(Action::Pause)) => (Some(OptionItem::Back), menu.selected),
+            (GameStatus::Options(menu), _) => (menu.on_key(key_code, action), menu.selected),
+            _ => return,
+        };
+
+        match item {
+            Some(OptionItem::Movement) => {
+                bindings.movement = bindings.movement.cycle();
+                let input = Input::Movement(bindings.movement);
+                self.record(input);
+            }
+            Some(OptionItem::Speed) => {
+                bindings.speed = next_speed(bindings.speed);
+                self.game_var.player.speed = bindings.speed;
+                let input = Input::Speed(bindings.speed);
+                self.record(input);
+            }
+            Some(OptionItem::Theme) => {
+                let GameStatic {
+                    ref mut theme,
+                    ref themes,
+                    ..
+                } = self.game_static;
+                *theme = (*theme + 1) % themes.len();
+                self.apply_theme();
+            }
+            Some(OptionItem::Back) => {
+                let mut menu = pause_menu();
+                menu.selected = 2;
+                self.game_var.game_status = GameStatus::Paused(menu);
+                self.show_menu();
+                return;
+            }
+            None => {}
+        }
+        // labels show the values
+        let GameStatic {
+            ref bindings,
+            ref themes,
+            theme,
+            ..
+        } = self.game_static;
+        let mut menu = options_menu(bindings, &themes[theme].name);
+        menu.selected = selected;
+        self.game_var.game_status = GameStatus::Options(menu);
+        self.show_menu();
+    }
+
+    /// after death, victory or the records, `Enter` or `Esc` goes on
+    fn on_end_event(&mut self, game: &mut Game, key_code: KeyCode) {
+        if let GameStatus::Records(page) = self.game_var.game_status {
+            let pages = self.game_static.records.pages();
+            let page = match self.game_static.bindings.action(key_code) {
+                Some(Action::MoveUp) => page.saturating_sub(1),
+                Some(Action::MoveDown) => (page + 1).min(pages - 1),
+                _ => page,
+            };
+            let text = self.game_static.records.text(page);
+            self.game_var.notifications.push(MessageType::Menu("Records".into(), text));
+            self.game_var.game_status = GameStatus::Records(page);
+        }
+        match (&self.game_var.game_status, key_code) {
+            (GameStatus::Died, KeyCode::Enter) => self.restart(game),
+            (GameStatus::Died, KeyCode::Esc)
+            | (GameStatus::Victory, KeyCode::Enter)
+            | (GameStatus::Records(_), KeyCode::Enter | KeyCode::Esc) => {
+                self.show_title(game, TITLE.into());
+            }
+            _ => {}
+        }
+    }
+
+    fn update_player_position(&mut self) {
+        if let GameStatus::Died = self.game_var.game_status {
+            return;
+        }
+        let GameVar {
+            ref dialogue,
+            ref cursor,
+            reading_log,
+            ..
+        } = self.game_var;
+        if dialogue.is_some() || cursor.is_some() || reading_log {
+            return;
+        }
+        let mut move_by = Position::from(&self.game_var.control);
+        let auto_walk = move_by.is_origin();
+        if auto_walk {
+            let GameVar {
+                ref mut player,
+                ref map_layers,
+                ref mut auto_path,
+                ..
+            } = self.game_var;
+            let next = match auto_path.last() {
+                Some(next) => *next,
+                None => {
+                    player.stand();
+                    return;
+                }
+            };
+            if !map_layers.is_walkable(&next) {
+                // something got in the way
+                auto_path.clear();
+                return;
+            }
+            move_by = Position(next.0 - player.position.0, next.1 - player.position.1);
+        }
+        self.record(Input::Move(move_by));
+        let GameVar {
+            ref player,
+            ref map_layers,
+            ..
+        } = self.game_var;
+        let rule = self.game_static.bindings.movement;
+        move_by = match rule.resolve(&player.position, move_by, |p| map_layers.is_barrier(p)) {
+            Some(move_by) => move_by,
+            None => return,
+        };
+        let next = player.position + move_by;
+
+        let GameVar {
+            ref mut player,
+            ref map_layers,
+            ..
+        } = self.game_var;
+        let cost = player.movement_cost(map_layers.tile(&next));
+        if !player.walk(cost, TICK_MILLIS as f32 / 1000.0) {
+            return;
+        }
+
+        // the stride is spent once it is known what walking into the tile does
+        if let Some(npc) = self.game_var.npcs.iter().position(|npc| npc.position == next) {
+            // talk to npc instead of walking into it
+            self.game_var.player.step(cost);
+            self.game_var.dialogue = Some(Dialogue::new(npc));
+            self.show_dialogue();
+            return;
+        }
+        if let Some(enemy) = self.game_var.enemies.iter().position(|e| e.position == next) {
+            self.game_var.player.step(cost);
+            self.attack(enemy);
+            return;
+        }
+        if self.game_var.map_layers.is_boulder(&next) {
+            if !self.try_push_boulder(next, move_by) {
+                // a stuck boulder is like standing still
+                self.game_var.player.stand();
+                return;
+            }
+        } else if self.game_var.map_layers.is_barrier(&next) {
+            // cannot move into barrier, but a door may open or a lever be pulled
+            self.game_var.player.step(cost);
+            self.try_open_door(next);
+            self.try_pull_lever(next);
+            return;
+        }
+
+        let GameVar {
+            ref mut player,
+            ref map_layers,
+            ref mut auto_path,
+            ..
+        } = self.game_var;
+        player.step(cost);
+        if auto_walk {
+            auto_path.pop();
+        }
+        player.move_to(next);
+        player.interact_background(map_layers);
+        self.game_var.moved = Some(move_by);
+
+        self.update_message_and_status();
+    }
+
+    /// slide over ice and drift with currents, a tile at a time
+    fn resolve_movement(&mut self) {
+        let mut move_by = self.game_var.moved.take();
+        let GameVar {
+            ref dialogue,
+            ref cursor,
+            reading_log,
+            ..
+        } = self.game_var;
+        // no drifting while the player is talking, aiming or reading
+        if dialogue.is_some() || cursor.is_some() || reading_log {
+            return;
+        }
+        let mut drifted = false;
+        for _ in 0..MAX_SLIDE {
+            if !matches!(self.game_var.game_status, GameStatus::Running) {
+                return;
+            }
+            let GameVar {
+                ref mut player,
+                ref map_layers,
+                ref mut auto_path,
+                ..
+            } = self.game_var;
+
+            // currents push once a tick, ice keeps the way the player went
+            let tile = map_layers.tile(&player.position);
+            let next_move = match (tile, move_by) {
+                (Some(Tile { slide: true, .. }), Some(move_by)) => move_by,
+                (Some(Tile { current: Some(direction), .. }), _) if !drifted => {
+                    drifted = true;
+                    Position::from(*direction)
+                }
+                _ => return,
+            };
+            // a diagonal slide keeps to the movement rule like a step does
+            let rule = self.game_static.bindings.movement;
+            let is_barrier = |p: &Position| map_layers.is_barrier(p);
+            let Some(next_move) = rule.resolve(&player.position, next_move, is_barrier) else {
+                return;
+            };
+            let next = player.position + next_move;
+            if !map_layers.is_walkable(&next) {
+                return;
+            }
+            auto_path.clear();
+            player.move_to(next);
+            player.interact_background(map_layers);
+            move_by = Some(next_move);
+
+            self.update_message_and_status();
+        }
+    }
+
+    /// return true if the boulder moved out of the way
+    fn try_push_boulder(&mut self, from: Position, move_by: Position) -> bool {
+        let GameVar {
+            ref player,
+            ref mut map_layers,
+            ref mut notifications,
+            ref mut events,
+            ref mut pushes,
+            ..
+        } = self.game_var;
+
+        // boulders can only be pushed straight
+        if move_by.0 != 0 && move_by.1 != 0 {
+            return false;
+        }
+        let to = from + move_by;
+        if !map_layers.is_free(&to) {
+            return false;
+        }
+        let filled = map_layers.push_boulder(&from, &to);
+        pushes.push(BoulderPush {
+            player: player.position,
+            from,
+            to,
+            filled,
+        });
+
+        if let Some(puzzle) = map_layers.plate(&to) {
+            if map_layers.is_puzzle_solved(puzzle) {
+                notifications.push(MessageType::Puzzle(puzzle));
+                events.push(Event::PuzzleSolved(puzzle));
+            }
+        }
+        true
+    }
+
+    fn try_open_door(&mut self, position: Position) {
+        let GameVar {
+            ref mut player,
+            ref mut map_layers,
+            ref mut notifications,
+            ..
+        } = self.game_var;
+
+        let door = map_layers.foregrounds.get(&position);
+        if let Some(&ForegroundVariant::Door { key, consume }) = door {
+            match player.bag.iter().position(|item| item == &key) {
+                Some(index) => {
+                    if consume {
+                        player.bag.remove(index);
+                    }
+                    map_layers.open_door(&position);
+                    notifications.push(MessageType::Unlock(key));
+                }
+                None => {
+                    notifications.push(MessageType::Locked(key));
+                }
+            }
+        }
+    }
+
+    fn try_pull_lever(&mut self, position: Position) {
+        let GameVar {
+            ref mut map_layers,
+            ref mut events,
+            ..
+        } = self.game_var;
+
+        if let Some((id, on)) = map_layers.pull_lever(&position) {
+            events.push(Event::PullLever(id, on));
+        }
+    }
+
+    fn show_dialogue(&mut self) {
+        let GameVar {
+            ref dialogue,
+            ref npcs,
+            ref flags,
+            ref mut notifications,
+            ..
+        } = self.game_var;
+
+        match dialogue {
+            Some(dialogue) => {
+                let npc = &npcs[dialogue.npc];
+                let text = dialogue.text(npc, |flag| flags.contains(flag));
+                notifications.push(MessageType::Dialogue(npc.name.clone(), text));
+            }
+            None => notifications.clear_modal(),
+        }
+    }
+
+    fn on_dialogue_event(&mut self, key_code: KeyCode) {
+        let input = match key_code {
+            KeyCode::Enter | KeyCode::Char(' ') => Some(Input::NextPage),
+            KeyCode::Char(c @ '1'..='9') => Some(Input::Choose(c as usize - '1' as usize)),
+            KeyCode::Esc => Some(Input::LeaveDialogue),
+            _ => None,
+        };
+        if let Some(input) = input {
+            self.record(input);
+        }
+        let GameVar {
+            ref mut dialogue,
+            ref npcs,
+            ref flags,
+            ..
+        } = self.game_var;
+        let current = match dialogue {
+            Some(current) => current,
+            None => return,
+        };
+        let npc = &npcs[current.npc];
+
+        match key_code {
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let has_choice = !npc.choices(current.node, |flag| flags.contains(flag)).is_empty();
+                if !current.next_page(npc) && !has_choice {
+                    *dialogue = None;
+                }
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                self.choose(c as usize - '1' as usize);
+            }
+            KeyCode::Esc => {
+                *dialogue = None;
+            }
+            _ => {}
+        }
+        self.show_dialogue();
+    }
+
+    /// pick the nth offered choice on the last page of the dialogue
+    fn choose(&mut self, number: usize) {
+        let GameVar {
+            ref mut dialogue,
+            ref npcs,
+            ref mut flags,
+            ref mut player,
+            ref mut events,
+            ref mut quests,
+            ref mut raw_quests,
+            ref history,
+            ref mut notifications,
+            ..
+        } = self.game_var;
+        let current = match dialogue {
+            Some(current) => current,
+            None => return,
+        };
+        let npc = &npcs[current.npc];
+        if !current.is_last_page(npc) {
+            return;
+        }
+        let choice = match npc.choices(current.node, |flag| flags.contains(flag)).get(number) {
+            Some(choice) => (*choice).clone(),
+            None => return,
+        };
+
+        for effect in choice.effects.iter() {
+            match effect {
+                DialogueEffect::SetFlag(flag) => {
+                    flags.insert(flag.clone());
+                    events.push(Event::Flag(flag.clone()));
+                }
+                DialogueEffect::Give(item) => {
+                    player.bag.push(*item);
+                    events.push(Event::Pickup(*item));
+                    if let Some(equipment) = self.game_static.raw_game_map.equipment_of(*item) {
+                        notifications.push(MessageType::Equip(equipment.name.clone()));
+                        player.equip(equipment.clone());
+                    }
+                }
+                DialogueEffect::MaxOxygen(oxygen) => {
+                    player.max_oxygen += oxygen;
+                }
+                DialogueEffect::StartQuest(quest) => {
+                    // picking the choice again does not start the quest twice
+                    if raw_quests.iter().any(|(q, _)| q == quest) {
+                        continue;
+                    }
+                    quests.push(quest.into());
+                    raw_quests.push((quest.clone(), history.len()));
+                }
+            }
+        }
+        match choice.next {
+            Some(node) => {
+                current.node = node;
+                current.page = 0;
+            }
+            None => *dialogue = None,
+        }
+    }
+
+    fn update_npcs(&mut self) {
+        let GameVar {
+            ref mut npcs,
+            ref mut map_layers,
+            ref player,
+            ref dialogue,
+            frame,
+            ..
+        } = self.game_var;
+        if frame % NPC_WANDER_TICKS != 0 {
+            return;
+        }
+
+        for (index, npc) in npcs.iter_mut().enumerate() {
+            let distance = match npc.wander {
+                Some(distance) => distance,
+                None => continue,
+            };
+            if dialogue.as_ref().is_some_and(|dialogue| dialogue.npc == index) {
+                continue;
+            }
+            // stand still half of the time
+            let move_by = match pseudo_random(frame as u64 * 31 + index as u64) % 8 {
+                0 => Position(0, -1),
+                1 => Position(0, 1),
+                2 => Position(-1, 0),
+                3 => Position(1, 0),
+                _ => continue,
+            };
+            let next = npc.position + move_by;
+            let home = self.game_static.raw_game_map.npcs[index].position;
+            if (next.0 - home.0).abs() > distance || (next.1 - home.1).abs() > distance {
+                continue;
+            }
+            if !map_layers.is_free(&next) || map_layers.is_water(&next) || next == player.position {
+                continue;
+            }
+            map_layers.move_npc(&npc.position, &next);
+            npc.position = next;
+        }
+    }
+
+    /// map position under a screen cell, None if outside the map view
+    fn screen_to_map(&self, game: &Game, column: u16, row: u16) -> Option<Position> {
+        let (width, height) = crossterm::terminal::size().ok()?;
+        let (_, (game_height, _)) = game.screen_size();
+        // termgame centers the screen and draws a border around the map
+        let left = width.saturating_sub(SCREEN_WIDTH) / 2 + 1;
+        let top = height.saturating_sub(SCREEN_HEIGHT) / 2 + 1;
+        if column < left || row < top {
+            return None;
+        }
+        if column >= left + SCREEN_WIDTH - 2 || row >= top + game_height - 2 {
+            return None;
+        }
+        let viewport = game.get_viewport();
+        Some(Position(
+            (column - left) as i32 + viewport.x,
+            (row - top) as i32 + viewport.y,
+        ))
+    }
+
+    /// auto walk to target along a path player does not drown on
+    fn walk_to(&mut self, target: Position) {
+        let GameVar {
+            ref player,
+            ref map_layers,
+            ref mut auto_path,
+            ref mut notifications,
+            ..
+        } = self.game_var;
+
+        let path = find_safe_path(
+            player.position,
+            target,
+            player.oxygen,
+            |p| map_layers.is_walkable(p),
+            |p, oxygen| player.oxygen_at(map_layers, p, oxygen),
+        );
+        match path {
+            Some(mut path) => {
+                path.reverse();
+                *auto_path = path;
+            }
+            None => {
+                notifications.push(MessageType::Walk("No safe way there".into()));
+            }
+        }
+    }
+
+    fn on_log_event(&mut self, key_code: KeyCode) {
+        let GameVar {
+            ref mut log,
+            ref mut reading_log,
+            ref mut notifications,
+            ..
+        } = self.game_var;
 
-struct GameStatic {
-    raw_game_map: RawGameMap,
-    screen_size: (u16, (u16, u16)),
-}
+        match (key_code, self.game_static.bindings.action(key_code)) {
+            (_, Some(Action::MoveUp)) => log.scroll_up(),
+            (_, Some(Action::MoveDown)) => log.scroll_down(),
+            (KeyCode::Esc, _) | (_, Some(Action::Log)) => {
+                *reading_log = false;
+                notifications.close_panel();
+                return;
+            }
+            _ => return,
+        }
+        notifications.push(MessageType::Log(log.text()));
+    }
 
-struct MyGame {
-    game_var: GameVar,
-    game_static: GameStatic,
-}
+    fn on_cursor_event(&mut self, key_code: KeyCode) {
+        let GameVar {
+            ref mut cursor,
+            ref mut map_layers,
+            ..
+        } = self.game_var;
+        let position = match cursor {
+            Some(position) => position,
+            None => return,
+        };
 
-impl MyGame {
-    fn new(raw_game_map: RawGameMap) -> Self {
-        let game_static = GameStatic {
-            raw_game_map,
-            screen_size: Default::default(),
+        let move_by = match (key_code, self.game_static.bindings.action(key_code)) {
+            (_, Some(Action::MoveLeft)) => Position(-1, 0),
+            (_, Some(Action::MoveRight)) => Position(1, 0),
+            (_, Some(Action::MoveUp)) => Position(0, -1),
+            (_, Some(Action::MoveDown)) => Position(0, 1),
+            (_, Some(Action::MoveUpLeft)) => Position(-1, -1),
+            (_, Some(Action::MoveUpRight)) => Position(1, -1),
+            (_, Some(Action::MoveDownLeft)) => Position(-1, 1),
+            (_, Some(Action::MoveDownRight)) => Position(1, 1),
+            (KeyCode::Enter, _) => {
+                let target = *position;
+                *cursor = None;
+                map_layers.set_cursor(None);
+                self.walk_to(target);
+                return;
+            }
+            (KeyCode::Esc, _) => {
+                *cursor = None;
+                map_layers.set_cursor(None);
+                return;
+            }
+            _ => return,
         };
-        Self {
-            game_var: Default::default(),
-            game_static,
-        }
+        *position += &move_by;
+        map_layers.set_cursor(Some(*position));
     }
 
-    fn init(&mut self, game: &Game) {
-        let q1 = StepQuest::new(BackgroundVariant::Water, 5);
-        let q2 = PickupQuest::new('x', 3);
+    fn update_enemies(&mut self) {
+        if let GameStatus::Died = self.game_var.game_status {
+            return;
+        }
+        let GameVar {
+            ref mut enemies,
+            ref mut map_layers,
+            ref mut player,
+            ref mut events,
+            frame,
+            ..
+        } = self.game_var;
+        if player.invulnerable > 0 {
+            player.invulnerable -= 1;
+        }
 
-        let q = CompoundQuest::new(vec![Box::new(q1), Box::new(q2)]);
+        let mut attackers = vec![];
+        for (index, enemy) in enemies.iter_mut().enumerate() {
+            if frame % enemy.speed.max(1) != 0 {
+                continue;
+            }
+            let sees = enemy.sees(&player.position);
+            if enemy.chasing && !sees {
+                events.push(Event::Evade(enemy.name.clone()));
+            }
+            enemy.chasing = sees;
 
-        self.game_static.screen_size = game.screen_size();
-        self.game_var = GameVar {
-            map_layers: MapLayers::from(&self.game_static.raw_game_map),
-            quests: vec![Box::new(q)],
-            ..Default::default()
+            let next = match enemy.next_step(map_layers, &player.position) {
+                Some(next) => next,
+                None => continue,
+            };
+            if next == player.position {
+                attackers.push(index);
+                continue;
+            }
+            if !enemy.can_walk(map_layers, &next) {
+                continue;
+            }
+            map_layers.move_npc(&enemy.position, &next);
+            enemy.position = next;
+        }
+        for index in attackers {
+            self.hurt_player(index);
         }
     }
 
-    fn update_player_position(&mut self) {
+    /// player walks into an enemy, defeating it if carrying its weakness
+    fn attack(&mut self, enemy: usize) {
+        let GameVar {
+            ref mut enemies,
+            ref mut map_layers,
+            ref player,
+            ref mut notifications,
+            ref mut events,
+            ..
+        } = self.game_var;
+
+        match enemies[enemy].weakness {
+            Some(item) if player.bag.contains(&item) => {
+                let enemy = enemies.remove(enemy);
+                map_layers.npcs.remove(&enemy.position);
+                map_layers.should_draw.push(enemy.position);
+                notifications.push(MessageType::Defeat(enemy.name.clone()));
+                events.push(Event::Defeat(enemy.name));
+            }
+            _ => self.hurt_player(enemy),
+        }
+    }
+
+    fn hurt_player(&mut self, enemy: usize) {
         let GameVar {
-            ref control,
+            ref enemies,
             ref mut player,
-            ref map_layers,
+            ref mut notifications,
             ..
         } = self.game_var;
-        let move_by = Position::from(control);
-        if move_by.is_origin() {
+        let Enemy { name, damage, .. } = &enemies[enemy];
+
+        if !player.hurt(*damage) {
             return;
         }
-        let next = player.position + move_by;
-        if map_layers.is_barrier(&next) {
-            // cannot move into barrier
+        self.game_var.auto_path.clear();
+        if player.health > 0 {
+            notifications.push(MessageType::Hurt(name.clone(), player.health));
             return;
         }
-        player.move_to(next);
-        player.interact_background(map_layers);
+        let reason = format!("You were killed by {name}");
+        self.die(reason);
+    }
 
-        self.update_message_and_status();
+    fn die(&mut self, reason: String) {
+        let GameVar {
+            ref mut notifications,
+            ref mut events,
+            ref mut game_status,
+            ..
+        } = self.game_var;
+
+        notifications.push(MessageType::Death(format!(
+            "{reason}\nEnter: restart, Esc: title screen"
+        )));
+        events.push(Event::Die(reason));
+        *game_status = GameStatus::Died;
     }
 
     fn update_message_and_status(&mut self) {
         let GameVar {
             ref mut player,
             ref mut map_layers,
-            ref mut message,
-            ref mut game_status,
+            ref mut notifications,
             ref mut events,
+            ref mut auto_path,
             ..
         } = self.game_var;
 
@@ -112,33 +1229,99 @@ impl MyGame {
         ));
 
         if let Some(foreground) = map_layers.foregrounds.get(&player.position) {
+            // stop auto walking to look at what is here
+            auto_path.clear();
             match foreground {
                 ForegroundVariant::Object(c) => {
                     player.bag.push(*c);
-                    *message = MessageType::Pickup(*c);
+                    notifications.push(MessageType::Pickup(*c));
                     events.push(Event::Pickup(*c));
+                    if let Some(equipment) = self.game_static.raw_game_map.equipment_of(*c) {
+                        notifications.push(MessageType::Equip(equipment.name.clone()));
+                        player.equip(equipment.clone());
+                    }
 
                     map_layers.remove_foreground(&player.position);
                 }
                 ForegroundVariant::Sign(s) => {
-                    *message = MessageType::Sign(s.clone());
+                    notifications.push(MessageType::Sign(s.clone()));
+                    events.push(Event::Read(player.position));
                 }
-            }
-        } else {
-            if let MessageType::Sign(_) = message {
-                *message = MessageType::None;
-            }
-            if let MessageType::Pickup(_) = message {
-                *message = MessageType::None;
+                ForegroundVariant::Door { .. }
+                | ForegroundVariant::Boulder
+                | ForegroundVariant::Lever { .. } => {}
             }
         }
 
         if player.oxygen <= 0 {
-            *message = MessageType::Death("You died from drown, press Enter to restart".into());
-            events.push(Event::Die(
-                "You died from drown, press Enter to restart".into(),
-            ));
-            *game_status = GameStatus::Died;
+            events.push(Event::Drown);
+            self.die("You died from drown".into());
+        }
+    }
+
+    /// set the flag of the time of day and redraw in its colours once it changes
+    fn update_time(&mut self) {
+        let GameVar {
+            frame,
+            ref mut map_layers,
+            ref mut flags,
+            ref mut events,
+            ..
+        } = self.game_var;
+
+        let time = TimeOfDay::at(frame);
+        if map_layers.time == Some(time) {
+            return;
+        }
+        if let Some(before) = map_layers.time {
+            flags.remove(before.flag());
+        }
+        flags.insert(time.flag().into());
+        map_layers.time = Some(time);
+        map_layers.redraw_all();
+        events.push(Event::Time(time));
+    }
+
+    /// move the shores with the tides, the player is in or out of water if under one
+    fn update_tides(&mut self) {
+        let GameVar {
+            frame,
+            ref mut player,
+            ref mut map_layers,
+            ..
+        } = self.game_var;
+
+        let mut under_player = false;
+        for tide in self.game_static.raw_game_map.tides.iter() {
+            let high = tide.is_high(frame);
+            for position in tide.shore.iter() {
+                if map_layers.set_tide(position, high) && *position == player.position {
+                    under_player = true;
+                }
+            }
+        }
+        if !under_player {
+            return;
+        }
+        player.interact_background(map_layers);
+        let running = matches!(self.game_var.game_status, GameStatus::Running);
+        if running && self.game_var.player.oxygen <= 0 {
+            self.game_var.events.push(Event::Drown);
+            self.die("You drowned in the rising tide".into());
+        }
+    }
+
+    /// tiles seen around the player, fewer at night unless a light is in the bag
+    fn sight_radius(&self) -> i32 {
+        let raw_game_map = &self.game_static.raw_game_map;
+        match self.game_var.map_layers.time {
+            Some(TimeOfDay::Night) => {
+                let bag = self.game_var.player.bag.iter();
+                let lights = bag.filter_map(|item| raw_game_map.lights.get(item));
+                let light = raw_game_map.light + lights.max().unwrap_or(&0);
+                light.min(raw_game_map.sight)
+            }
+            _ => raw_game_map.sight,
         }
     }
 
@@ -171,136 +1354,450 @@ impl MyGame {
             viewport_position.1 += 1;
         }
     }
-}
-
-impl Controller for MyGame {
-    fn on_start(&mut self, game: &mut Game) {
-        self.init(game);
 
+    #[allow(clippy::single_match)]
+    fn on_running_event(&mut self, game: &mut Game, event: GameEvent) {
         let GameVar {
+            ref mut game_status,
+            ref mut control,
+            ref mut notifications,
             ref mut player,
             ref mut map_layers,
+            ref mut pushes,
+            ref mut cursor,
+            ref mut auto_path,
+            ref quests,
+            ref timer,
+            ref frame,
+            ref statistics,
             ..
         } = self.game_var;
-        player.move_to(Position(3, 3));
-
-        map_layers.update_player(player);
-    }
+        let GameStatic { ref bindings, .. } = self.game_static;
+        if self.game_var.dialogue.is_some() {
+            if let SimpleEvent::Just(key_code) = event.into() {
+                self.on_dialogue_event(key_code);
+            }
+            return;
+        }
 
-    fn on_event(&mut self, game: &mut Game, event: GameEvent) {
-        let GameVar {
-            ref mut control,
-            ref mut message,
-            ref game_status,
-            ref player,
-            ref quests,
+        if cursor.is_some() {
+            if let SimpleEvent::Just(key_code) = event.into() {
+                self.on_cursor_event(key_code);
+            }
+            return;
+        }
+        if self.game_var.reading_log {
+            if let SimpleEvent::Just(key_code) = event.into() {
+                self.on_log_event(key_code);
+            }
+            return;
+        }
+        if let GameEvent::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(_),
+            column,
+            row,
             ..
-        } = self.game_var;
-        match game_status {
-            GameStatus::Died => {
-                match event.into() {
-                    SimpleEvent::Just(KeyCode::Enter) => {
-                        self.init(game);
-                        self.on_start(game);
-                    }
-                    _ => {}
-                }
-                return;
+        }) = event
+        {
+            if let Some(target) = self.screen_to_map(game, column, row) {
+                self.walk_to(target);
             }
-            _ => {}
+            return;
         }
 
         match event.into() {
             SimpleEvent::Just(key_code) => {
-                match key_code {
-                    KeyCode::Char(ch) => {
-                        match ch {
-                            't' => {
-                                // debug message
-                                if let MessageType::Debug(_) = message {
-                                    *message = MessageType::None;
-                                } else {
-                                    *message = MessageType::Debug(format!(
-                                        "player pos: {}",
-                                        ron::to_string(&player.position).unwrap()
-                                    ));
-                                }
-                            }
-                            'b' => {
-                                // check bag
-                                if let MessageType::Bag(_) = message {
-                                    *message = MessageType::None;
-                                } else {
-                                    *message = MessageType::Bag(format!("{:?}", player.bag));
-                                }
-                            }
-
-                            'q' => {
-                                // check quest
-                                if let MessageType::Quest(_) = message {
-                                    *message = MessageType::None;
-                                } else {
-                                    *message = MessageType::Quest(quests[0].to_string());
-                                }
-                            }
-                            _ => {}
+                // any key stops auto walking
+                auto_path.clear();
+                let action = match bindings.action(key_code) {
+                    Some(action) => action,
+                    None => return,
+                };
+                match action {
+                    Action::Debug => {
+                        // debug message
+                        let tile = map_layers.tile(&player.position);
+                        notifications.toggle(MessageType::Debug(format!(
+                            "player pos: {} on {}, time: {} ({})",
+                            ron::to_string(&player.position).unwrap(),
+                            tile.map_or("nothing", |tile| tile.name.as_str()),
+                            clock_text(*frame),
+                            TimeOfDay::at(*frame)
+                        )));
+                    }
+                    Action::ToggleBag => {
+                        // check bag
+                        let bag = format!("{:?}\n{}", player.bag, player.stats());
+                        notifications.toggle(MessageType::Bag(bag));
+                    }
+                    Action::Target => {
+                        // pick a place to walk to
+                        *cursor = Some(player.position);
+                        map_layers.set_cursor(*cursor);
+                    }
+                    Action::Undo => {
+                        self.game_var.inputs.push((statistics.ticks, Input::Undo));
+                        // undo last boulder push, only while the player stands where it
+                        // left them and can step back
+                        let undoable = |push: &mut BoulderPush| {
+                            push.from == player.position && map_layers.is_walkable(&push.player)
                         };
+                        if let Some(push) = pushes.pop_if(undoable) {
+                            map_layers.undo_push(&push);
+                            player.move_to(push.player);
+                        }
+                    }
+                    Action::ToggleQuest => {
+                        // check quest
+                        let status = quests.iter().map(|q| q.to_string());
+                        let status = status.collect::<Vec<_>>().join("\n");
+                        notifications.toggle(MessageType::Quest(status));
+                    }
+                    Action::Map => {
+                        self.game_var.map_view = self.game_var.map_view.next();
+                    }
+                    Action::Log => {
+                        self.game_var.log.reset_scroll();
+                        self.game_var.reading_log = true;
+                        let text = self.game_var.log.text();
+                        self.game_var.notifications.push(MessageType::Log(text));
+                        return;
+                    }
+                    Action::Timer => {
+                        let text = timer.text(statistics.ticks, TICK_MILLIS);
+                        notifications.toggle(MessageType::Timer(text));
+                    }
+                    Action::Help => {
+                        notifications.toggle(MessageType::Help(bindings.help()));
+                    }
+                    Action::Pause => {
+                        *game_status = GameStatus::Paused(pause_menu());
+                        self.show_menu();
+                        return;
                     }
                     _ => {}
                 };
-                control.update(key_code);
+                control.update(action);
             }
             _ => {}
         }
     }
 
-    fn on_tick(&mut self, game: &mut Game) {
+    /// one tick of the running game
+    fn update(&mut self) {
         self.update_player_position();
+        self.resolve_movement();
+        self.game_var.player.breathe(&self.game_var.map_layers, self.game_var.frame);
+        self.update_npcs();
+        self.update_enemies();
         self.update_viewport_position();
+        self.update_time();
+        self.update_tides();
+        let sight = self.sight_radius();
 
         let GameVar {
             ref mut player,
             ref mut map_layers,
             ref mut control,
-            ref viewport_position,
-            ref mut message,
+            ref mut notifications,
             ref mut frame,
             ref mut events,
             ref mut quests,
+            ref mut triggers,
+            ref mut history,
+            ref mut statistics,
+            ref raw_quests,
+            ref mut timer,
+            ref mut log,
             ..
         } = self.game_var;
+        let GameStatic {
+            ref mut records, ..
+        } = self.game_static;
 
         map_layers.update_player(player);
-
-        for (Position(x, y), sc) in map_layers.get_style_characters(&player) {
-            game.set_screen_char(x, y, sc);
+        if let Some(explored) = map_layers.update_sight(sight) {
+            events.push(Event::Explore(explored));
         }
 
+        // completed quests are reported on next tick
+        let handled = history.len();
+        let mut completed = vec![];
         for event in events.drain(..) {
-            for quest in quests.iter_mut() {
-                quest.update(&event)
+            for (index, quest) in quests.iter_mut().enumerate() {
+                let before = quest.status();
+                quest.update(&event);
+                if before != QuestStatus::Completed && quest.is_completed() {
+                    completed.push(Event::QuestCompleted(index));
+                }
+                let raw_quest = &raw_quests[index].0;
+                if raw_quest.milestone(before, quest.status()).is_some() {
+                    log.push(statistics.ticks, &format!("Quest: {quest}"));
+                    notifications.push(MessageType::Progress(quest.to_string()));
+                }
+                if timer.update(statistics.ticks, index, raw_quest, before, quest.status()) {
+                    let text = timer.split_text(timer.splits.len() - 1);
+                    notifications.push(MessageType::Split(text));
+                }
+            }
+            for trigger in triggers.iter_mut() {
+                trigger.update(&event, map_layers, notifications);
+            }
+            match &event {
+                Event::Read(position) => {
+                    if let Some(ForegroundVariant::Sign(s)) = map_layers.foregrounds.get(position) {
+                        log.push(statistics.ticks, &format!("Sign: {s}"));
+                    }
+                }
+                Event::Pickup(item) => log.push(statistics.ticks, &format!("Picked up '{item}'")),
+                Event::Die(reason) => log.push(statistics.ticks, reason),
+                _ => {}
             }
+            statistics.update(&event);
+            records.totals.update(&event);
+            history.push((statistics.ticks, event));
         }
+        events.extend(completed);
+        notifications.refresh(MessageType::Timer(timer.text(statistics.ticks, TICK_MILLIS)));
+        notifications.refresh(MessageType::Log(log.text()));
+        notifications.tick();
+
         control.clear();
+        statistics.ticks += 1;
+        *frame += 1;
+
+        let new_events = history[handled..].iter().map(|(_, event)| event.clone());
+        for event in new_events.collect::<Vec<_>>() {
+            for name in self.update_achievements(&event) {
+                let raw = self.game_static.raw_achievements.iter().find(|a| a.name == name);
+                let description = raw.map(|a| a.description.clone()).unwrap_or_default();
+                let achievement = MessageType::Achievement(name, description);
+                self.game_var.notifications.push(achievement);
+            }
+        }
+
+        // let completed quests fire their triggers first, a death this tick is no win
+        let running = matches!(self.game_var.game_status, GameStatus::Running);
+        if running && self.game_var.events.is_empty() && self.is_won() {
+            self.win();
+        }
+    }
+
+    fn is_won(&self) -> bool {
+        let GameVar {
+            ref quests,
+            ref player,
+            ref map_layers,
+            ..
+        } = self.game_var;
+        let win = &self.game_static.raw_game_map.win;
+
+        !win.is_empty()
+            && win.iter().all(|condition| match condition {
+                WinCondition::Quests => quests.iter().all(|quest| quest.is_completed()),
+                WinCondition::Exit(position) => player.position == *position,
+                WinCondition::Objects => !map_layers
+                    .foregrounds
+                    .values()
+                    .any(|f| matches!(f, ForegroundVariant::Object(_))),
+            })
+    }
+
+    fn win(&mut self) {
+        let unlocked = self.update_achievements(&Event::Win);
+        let GameVar {
+            ref mut notifications,
+            ref mut game_status,
+            ref statistics,
+            ref mut timer,
+            ref inputs,
+            ..
+        } = self.game_var;
+        let GameStatic {
+            ref mut records,
+            ref map_path,
+            ..
+        } = self.game_static;
+
+        timer.finish(statistics.ticks);
+        let mut finish = timer.split_text(timer.splits.len() - 1);
+        if timer.is_best() {
+            finish += ", personal best";
+            let written = write_splits(splits_path(map_path), &timer.splits)
+                .and_then(|_| write_inputs(inputs_path(map_path), inputs));
+            if let Err(e) = written {
+                finish += &format!(" (cannot save: {e})");
+            }
+        }
+
+        let map = map_path.file_stem().unwrap_or_default().to_string_lossy();
+        let broken = records.complete(&map, statistics);
+        let mut summary = format!("{}\n{finish}", statistics.summary(TICK_MILLIS));
+        if !broken.is_empty() {
+            summary += &format!("\nNew record: {}", broken.join(", "));
+        }
+        if !unlocked.is_empty() {
+            summary += &format!("\nAchievement: {}", unlocked.join(", "));
+        }
+        notifications.push(MessageType::Victory(format!("{summary}\nEnter: title screen")));
+        *game_status = GameStatus::Victory;
+        self.save_records();
+    }
+
+    fn save_records(&mut self) {
+        if let Err(e) = write_records(RECORDS_PATH, &self.game_static.records) {
+            let notice = MessageType::Notice(format!("Cannot save records: {e}"));
+            self.game_var.notifications.push(notice);
+        }
+    }
+}
+
+impl Controller for MyGame {
+    fn on_start(&mut self, game: &mut Game) {
+        // on the alternate screen, which keeps keyboard flags of its own in kitty
+        push_keyboard_flags();
+        self.show_title(game, TITLE.into());
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: GameEvent) {
+        // only presses go on, releases and repeats just update held keys
+        if let GameEvent::Key(KeyEvent { code, kind, .. }) = event {
+            let action = self.game_static.bindings.action(code);
+            match (kind, action) {
+                (KeyEventKind::Press, _) => {}
+                (KeyEventKind::Release, Some(action)) => {
+                    self.game_var.control.release(action);
+                    return;
+                }
+                (KeyEventKind::Repeat, Some(action)) => {
+                    self.game_var.control.update(action);
+                    return;
+                }
+                _ => return,
+            }
+        }
+        if let SimpleEvent::WithControl(KeyCode::Char('c')) = event.clone().into() {
+            quit(game);
+            return;
+        }
+        let key_code = match event.clone().into() {
+            SimpleEvent::Just(key_code) => Some(key_code),
+            _ => None,
+        };
+
+        match (&self.game_var.game_status, key_code) {
+            (GameStatus::Running, _) => self.on_running_event(game, event),
+            (GameStatus::Title(_), Some(key_code)) => self.on_title_event(game, key_code),
+            (GameStatus::Paused(_), Some(key_code)) => self.on_pause_event(game, key_code),
+            (GameStatus::Options(_), Some(key_code)) => self.on_options_event(key_code),
+            (GameStatus::Died | GameStatus::Victory | GameStatus::Records(_), Some(key_code)) => {
+                self.on_end_event(game, key_code);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        if let GameStatus::Running = self.game_var.game_status {
+            self.update();
+        }
+
+        let GameVar {
+            ref player,
+            ref mut map_layers,
+            ref viewport_position,
+            ref notifications,
+            ref quests,
+            ref raw_quests,
+            map_view,
+            ref mut overlay,
+            ..
+        } = self.game_var;
+        map_layers.should_draw.append(overlay);
+        for (Position(x, y), sc) in map_layers.get_style_characters(player) {
+            game.set_screen_char(x, y, sc);
+        }
         game.set_viewport(<Position>::into(*viewport_position));
-        game.set_message(message.clone().into());
+        game.set_message(notifications.current().into());
 
-        *frame += 1;
+        // map overlay on top of the view, sized after the message
+        let (width, (game_height, _)) = game.screen_size();
+        let area = map_view.area(width as i32 - 2, game_height as i32 - 2);
+        if let Some((left, top, width, height)) = area {
+            let pending = quests.iter().zip(raw_quests.iter());
+            let pending = pending.filter(|(quest, _)| !quest.is_completed());
+            let targets = pending.flat_map(|(_, (raw_quest, _))| raw_quest.targets(map_layers));
+            let targets = targets.chain(map_layers.exits.iter().cloned()).collect();
+            let cells = render_minimap(map_layers, player.icon, &targets, (width, height));
+            for (Position(x, y), sc) in cells {
+                let position = *viewport_position + Position(left + x, top + y);
+                game.set_screen_char(position.0, position.1, Some(sc));
+                overlay.push(position);
+            }
+        }
     }
 }
 
+/// ask the terminal to report key releases, ignored where not supported
+fn push_keyboard_flags() {
+    let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+    let _ = execute!(stdout(), PushKeyboardEnhancementFlags(flags));
+}
+
+/// end the game, restoring the keyboard while still on the alternate screen
+fn quit(game: &mut Game) {
+    let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+    game.end_game();
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    // let game_map = read_map_data("../maps/full_game.ron")?;
-    let game_map = read_map_data("../maps/testing_game.ron")?;
+    // flags on the command line win over what the terminal seems to support
+    let render = RenderMode::detect().with_args(env::args().skip(1))?;
+
+    let bindings = if Path::new("../bindings.ron").exists() {
+        read_bindings("../bindings.ron")?
+    } else {
+        Bindings::default()
+    };
+
+    let records = if Path::new(RECORDS_PATH).exists() {
+        read_records(RECORDS_PATH)?
+    } else {
+        Records::default()
+    };
+
+    let raw_achievements = if Path::new(ACHIEVEMENTS_PATH).exists() {
+        read_achievements(ACHIEVEMENTS_PATH)?
+    } else {
+        vec![]
+    };
+
+    let tileset = if Path::new(TILESET_PATH).exists() {
+        read_tileset(TILESET_PATH)?
+    } else {
+        Tileset::builtin()
+    };
+
+    let themes = if Path::new(THEMES_PATH).exists() {
+        read_themes(THEMES_PATH)?
+    } else {
+        builtin_themes()
+    };
 
-    let mut controller = MyGame::new(game_map);
+    let mut controller =
+        MyGame::new(bindings, records, raw_achievements, tileset, themes, render);
 
-    run_game(
+    // ctrl + c is handled by the game, to restore the keyboard before leaving
+    let result = run_game(
         &mut controller,
         GameSettings::new()
-            .tick_duration(Duration::from_millis(50))
-            .quit_event(Some(SimpleEvent::WithControl(KeyCode::Char('c')).into())),
-    )?;
+            .tick_duration(Duration::from_millis(TICK_MILLIS))
+            .quit_event(None),
+    );
+    result?;
+    // lifetime totals count unfinished games too
+    write_records(RECORDS_PATH, &controller.game_static.records)?;
     println!("Game Ended!");
     Ok(())
 }
@@ -3,54 +3,280 @@ use std::error::Error;
 use std::fs::read_to_string;
 use std::path::Path;
 
-use termgame::StyledCharacter;
+use serde::{Deserialize, Serialize};
+use termgame::{Font, GameStyle, StyledCharacter};
 
+use crate::clock::TimeOfDay;
+use crate::enemy::Enemy;
+use crate::equipment::Equipment;
+use crate::npc::Npc;
+use crate::plain::{to_ascii, RenderMode};
 use crate::player::Player;
-use crate::utils::{
-    BackgroundVariant, ForegroundVariant, MapObjectVariant, Position, RawMapObject,
-};
+use crate::quest::{RawQuest, WinCondition};
+use crate::theme::Theme;
+use crate::tide::Tide;
+use crate::tileset::{Tile, TileId, Tileset, BARRIER, PLATE, ROCK, SAND, WATER};
+use crate::trigger::Trigger;
+use crate::utils::{ForegroundVariant, Item, MapObjectVariant, Position, RawMapObject};
 
 const FLAG: char = '⚑';
+const DOOR: char = '▯';
+const BOULDER: char = '●';
+const LEVER_ON: char = '╱';
+const LEVER_OFF: char = '╲';
+const CURSOR: char = '✛';
+const EXIT: char = '⚐';
 
-pub type RawGameMap = HashMap<Position, RawMapObject>;
+pub type RawTiles = HashMap<Position, RawMapObject>;
 
+fn default_start() -> Position {
+    Position(3, 3)
+}
+
+fn default_win() -> Vec<WinCondition> {
+    vec![WinCondition::Quests]
+}
+
+fn default_sight() -> i32 {
+    8
+}
+
+fn default_fog() -> bool {
+    true
+}
+
+fn default_light() -> i32 {
+    3
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RawGameMap {
+    pub tiles: RawTiles,
+    /// where the player starts
+    #[serde(default = "default_start")]
+    pub start: Position,
+    /// quests to complete to win, the default ones if empty
+    #[serde(default)]
+    pub quests: Vec<RawQuest>,
+    /// the map is won once all of them hold
+    #[serde(default = "default_win")]
+    pub win: Vec<WinCondition>,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+    #[serde(default)]
+    pub npcs: Vec<Npc>,
+    #[serde(default)]
+    pub enemies: Vec<Enemy>,
+    /// how many tiles far the player sees
+    #[serde(default = "default_sight")]
+    pub sight: i32,
+    /// hide tiles out of sight, the whole map is shown if false
+    #[serde(default = "default_fog")]
+    pub fog: bool,
+    /// how many tiles far the player sees at night
+    #[serde(default = "default_light")]
+    pub light: i32,
+    /// items lighting more tiles at night while in the bag, e.g. a torch
+    #[serde(default)]
+    pub lights: HashMap<Item, i32>,
+    #[serde(default)]
+    pub tides: Vec<Tide>,
+    /// items put on once picked up
+    #[serde(default)]
+    pub equipment: Vec<Equipment>,
+    /// tiles of the map, added to the tileset file; the whole tileset once loaded
+    #[serde(default)]
+    pub tileset: Tileset,
+}
+
+impl Default for RawGameMap {
+    fn default() -> Self {
+        RawGameMap {
+            tiles: Default::default(),
+            start: default_start(),
+            quests: Default::default(),
+            win: default_win(),
+            triggers: Default::default(),
+            npcs: Default::default(),
+            enemies: Default::default(),
+            sight: default_sight(),
+            fog: default_fog(),
+            light: default_light(),
+            lights: Default::default(),
+            tides: Default::default(),
+            equipment: Default::default(),
+            tileset: Default::default(),
+        }
+    }
+}
+
+impl RawGameMap {
+    /// icons of the characters drawn without unicode, by their icon
+    pub fn ascii_icons(&self) -> HashMap<char, char> {
+        let npcs = self.npcs.iter().map(|npc| (npc.icon, npc.ascii_icon()));
+        let enemies = self.enemies.iter().map(|enemy| (enemy.icon, enemy.ascii_icon()));
+        npcs.chain(enemies).collect()
+    }
+
+    /// equipment put on once the item is picked up, if any
+    pub fn equipment_of(&self, item: Item) -> Option<&Equipment> {
+        self.equipment.iter().find(|equipment| equipment.item == item)
+    }
+
+    /// id of a tile placed on the map or named by its quests but missing from its tileset,
+    /// if any
+    pub fn unknown_tile(&self) -> Option<&TileId> {
+        let placed = self.tiles.values().filter_map(|tile| match tile {
+            RawMapObject::Tile(id) => Some(id),
+            _ => None,
+        });
+        let started = self.npcs.iter().flat_map(|npc| npc.quests());
+        let named = self.quests.iter().chain(started).flat_map(|quest| quest.tiles());
+        placed.chain(named).find(|id| !self.tileset.contains(id))
+    }
+}
+
+/// a map file is either a bare map of tiles, or a RawGameMap struct
 pub fn read_map_data<P: AsRef<Path>>(path: P) -> Result<RawGameMap, Box<dyn Error>> {
     let content = read_to_string(path)?;
+    if let Ok(tiles) = ron::from_str::<RawTiles>(&content) {
+        return Ok(RawGameMap {
+            tiles,
+            ..Default::default()
+        });
+    }
     let game_map = ron::from_str::<RawGameMap>(&content)?;
     Ok(game_map)
 }
 
-#[derive(Default)]
+/// a boulder push, kept to be undone
+pub struct BoulderPush {
+    /// where player stood before pushing
+    pub player: Position,
+    pub from: Position,
+    pub to: Position,
+    /// water tile the boulder sank into, turning it into rock
+    pub filled: Option<TileId>,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct MapLayers {
     pub player: Position,
     pub foregrounds: HashMap<Position, ForegroundVariant>,
-    pub backgrounds: HashMap<Position, BackgroundVariant>,
+    /// tile ids of the tileset
+    pub backgrounds: HashMap<Position, TileId>,
+    #[serde(skip)]
     pub should_draw: Vec<Position>,
     pub waters: HashSet<Position>,
     pub barriers: HashSet<Position>,
+    /// backgrounds covered by barriers raised by triggers
+    pub covered: HashMap<Position, Option<TileId>>,
+    /// puzzle ids of pressure plates
+    #[serde(default)]
+    pub plates: HashMap<Position, char>,
+    /// icons of non-player characters, enemies included
+    pub npcs: HashMap<Position, char>,
+    /// tiles to reach to win
+    pub exits: HashSet<Position>,
+    #[serde(default)]
+    pub fog: bool,
+    /// drawn in darker colours out of sight at night
+    #[serde(skip)]
+    pub time: Option<TimeOfDay>,
+    /// tiles of the map ever in sight
+    #[serde(default)]
+    pub explored: HashSet<Position>,
+    /// tiles in sight of the player now
+    #[serde(skip)]
+    pub visible: HashSet<Position>,
+    #[serde(skip)]
+    pub cursor: Option<Position>,
+    /// set from the map once loaded, in the colours of the theme
+    #[serde(skip)]
+    pub tileset: Tileset,
+    #[serde(skip)]
+    pub theme: Theme,
+    #[serde(skip)]
+    pub render: RenderMode,
+    /// icons of characters drawn without unicode, set from the map
+    #[serde(skip)]
+    pub ascii_icons: HashMap<char, char>,
 }
 
 impl MapLayers {
     /// render a position into StyledCharacter
     pub fn get(&self, player: &Player, position: &Position) -> Option<StyledCharacter> {
-        let mut sc = StyledCharacter::new(' ');
+        // tiles seen before are drawn dimmed, without who stands there
+        let night = self.time == Some(TimeOfDay::Night);
+        let in_sight = self.visible.contains(position) || !(self.fog || night);
+        if self.fog && !in_sight && !self.explored.contains(position) {
+            return None;
+        }
+        let tile = self.tile(position);
+        let mut sc = StyledCharacter::new(tile.map_or(' ', |tile| tile.glyph_in(self.render)));
+        // the theme has its say on what is drawn on top
+        let mut look = None;
+        if self.exits.contains(position) {
+            sc.c = EXIT;
+        }
         if let Some(foreground) = self.foregrounds.get(position) {
             match foreground {
                 ForegroundVariant::Object(c) => {
                     sc.c = *c;
+                    look = Some(&self.theme.object);
                 }
                 ForegroundVariant::Sign(_) => {
                     sc.c = FLAG;
+                    look = Some(&self.theme.sign);
+                }
+                ForegroundVariant::Door { .. } => {
+                    sc.c = DOOR;
+                }
+                ForegroundVariant::Boulder => {
+                    sc.c = BOULDER;
+                }
+                ForegroundVariant::Lever { on, .. } => {
+                    sc.c = if *on { LEVER_ON } else { LEVER_OFF };
                 }
             }
         }
 
-        if let Some(background) = self.backgrounds.get(position) {
-            sc.style = Some(background.into());
+        if let Some(tile) = tile {
+            sc.style = Some(match (in_sight, night) {
+                (true, _) => tile.style(),
+                (false, true) => tile.night_style(),
+                (false, false) => tile.dimmed_style(),
+            });
+        }
+
+        if let Some(icon) = self.npcs.get(position).filter(|_| in_sight) {
+            sc.c = match self.render.ascii {
+                true => self.ascii_icons.get(icon).copied().unwrap_or(*icon),
+                false => *icon,
+            };
+            look = None;
         }
 
         if self.player == *position {
             sc.c = player.icon;
+            look = Some(&self.theme.player);
+        }
+
+        if self.cursor == Some(*position) {
+            sc.c = CURSOR;
+            look = None;
+        }
+
+        if let Some(look) = look {
+            look.apply(&mut sc);
+        }
+
+        if self.render.ascii {
+            sc.c = to_ascii(sc.c);
+        }
+        if self.render.monochrome {
+            // no colours to darken, so tiles out of sight are dim
+            sc.style = (!in_sight).then(|| GameStyle::new().font(Some(Font::DIM)));
         }
 
         Some(sc)
@@ -70,12 +296,45 @@ impl MapLayers {
         self.should_draw.push(position);
         player.update_draw = false;
     }
+    /// tile of the background at position
+    pub fn tile(&self, position: &Position) -> Option<&Tile> {
+        self.backgrounds.get(position).and_then(|id| self.tileset.get(id))
+    }
     pub fn is_barrier(&self, position: &Position) -> bool {
         self.barriers.contains(position)
     }
     pub fn is_water(&self, position: &Position) -> bool {
         self.waters.contains(position)
     }
+    /// a tile an npc or a pushed boulder can move onto
+    pub fn is_free(&self, position: &Position) -> bool {
+        !self.is_barrier(position)
+            && !self.foregrounds.contains_key(position)
+            && !self.npcs.contains_key(position)
+    }
+    /// a tile the player can walk onto
+    pub fn is_walkable(&self, position: &Position) -> bool {
+        !self.is_barrier(position) && !self.npcs.contains_key(position)
+    }
+    pub fn set_cursor(&mut self, cursor: Option<Position>) {
+        if let Some(position) = self.cursor {
+            self.should_draw.push(position);
+        }
+        if let Some(position) = cursor {
+            self.should_draw.push(position);
+        }
+        self.cursor = cursor;
+    }
+    pub fn move_npc(&mut self, from: &Position, to: &Position) {
+        if let Some(icon) = self.npcs.remove(from) {
+            self.npcs.insert(*to, icon);
+            self.should_draw.push(*from);
+            self.should_draw.push(*to);
+        }
+    }
+    pub fn is_boulder(&self, position: &Position) -> bool {
+        self.foregrounds.get(position) == Some(&ForegroundVariant::Boulder)
+    }
     pub fn get_style_characters(&mut self, player: &Player) -> Vec<(Position, Option<StyledCharacter>)> {
         let positions = self.should_draw.drain(..).collect::<Vec<_>>();
         positions
@@ -83,33 +342,225 @@ impl MapLayers {
             .map(|position| (position, self.get(player, &position)))
             .collect()
     }
+    /// a barrier but a lever, which is seen past
+    fn blocks_sight(&self, position: &Position) -> bool {
+        let foreground = self.foregrounds.get(position);
+        self.is_barrier(position) && !matches!(foreground, Some(ForegroundVariant::Lever { .. }))
+    }
+    /// no tile between blocks the sight, the tiles themselves may
+    fn in_line_of_sight(&self, from: &Position, to: &Position) -> bool {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let steps = dx.abs().max(dy.abs());
+        (1..steps).all(|i| {
+            let x = ((dx * i) as f32 / steps as f32).round() as i32;
+            let y = ((dy * i) as f32 / steps as f32).round() as i32;
+            !self.blocks_sight(&Position(from.0 + x, from.1 + y))
+        })
+    }
+    /// see what is in the sight radius of the player, return the explored percent if it grew
+    pub fn update_sight(&mut self, sight: i32) -> Option<usize> {
+        let Position(x, y) = self.player;
+        let mut visible = HashSet::new();
+        for dx in -sight..=sight {
+            for dy in -sight..=sight {
+                let position = Position(x + dx, y + dy);
+                if dx * dx + dy * dy <= sight * sight
+                    && self.in_line_of_sight(&self.player, &position)
+                {
+                    visible.insert(position);
+                }
+            }
+        }
+        let changed = visible.symmetric_difference(&self.visible);
+        self.should_draw.extend(changed);
+
+        let is_tile = |p: &&Position| {
+            self.backgrounds.contains_key(p) || self.foregrounds.contains_key(p)
+        };
+        let new = visible.iter().filter(is_tile).filter(|p| !self.explored.contains(p));
+        let new = new.cloned().collect::<Vec<_>>();
+        self.visible = visible;
+        if new.is_empty() {
+            return None;
+        }
+        let before = self.explored_percent();
+        self.explored.extend(new);
+        let after = self.explored_percent();
+        (after > before).then_some(after)
+    }
+    /// share of the tiles of the map ever seen
+    pub fn explored_percent(&self) -> usize {
+        let tiles = self.backgrounds.keys().chain(self.foregrounds.keys());
+        let tiles = tiles.collect::<HashSet<_>>().len();
+        self.explored.len() * 100 / tiles.max(1)
+    }
+    pub fn signs(&self) -> HashSet<Position> {
+        let signs = self.foregrounds.iter();
+        let signs = signs.filter(|(_, f)| matches!(f, ForegroundVariant::Sign(_)));
+        signs.map(|(position, _)| *position).collect()
+    }
+    /// look the way the theme has it, drawing everything again
+    pub fn set_theme(&mut self, tileset: &Tileset, theme: &Theme) {
+        self.tileset = tileset.themed(theme);
+        self.theme = theme.clone();
+        self.redraw_all();
+    }
+    /// draw every tile again, e.g. on a cleared screen
+    pub fn redraw_all(&mut self) {
+        let positions = self.backgrounds.keys().chain(self.foregrounds.keys());
+        let positions = positions.chain(self.npcs.keys()).chain(self.exits.iter());
+        let positions = positions.cloned().collect::<Vec<_>>();
+        self.should_draw.extend(positions);
+        self.should_draw.push(self.player);
+    }
     pub fn remove_foreground(&mut self, position: &Position) {
         self.foregrounds.remove(position);
         self.should_draw.push(*position);
     }
+    /// remove the door at position, leaving the tile walkable
+    pub fn open_door(&mut self, position: &Position) {
+        self.remove_foreground(position);
+        self.barriers.remove(position);
+    }
+    /// move boulder from one position to another, return the water tile it filled if any
+    pub fn push_boulder(&mut self, from: &Position, to: &Position) -> Option<TileId> {
+        self.remove_foreground(from);
+        self.barriers.remove(from);
+        self.should_draw.push(*to);
+        if self.is_water(to) {
+            let filled = self.backgrounds.get(to).cloned().unwrap_or(WATER.into());
+            self.set_background(to, ROCK.into());
+            return Some(filled);
+        }
+        self.foregrounds.insert(*to, ForegroundVariant::Boulder);
+        self.barriers.insert(*to);
+        None
+    }
+    pub fn undo_push(&mut self, push: &BoulderPush) {
+        if let Some(filled) = &push.filled {
+            self.set_background(&push.to, filled.clone());
+        } else {
+            self.remove_foreground(&push.to);
+            self.barriers.remove(&push.to);
+        }
+        self.foregrounds.insert(push.from, ForegroundVariant::Boulder);
+        self.barriers.insert(push.from);
+        self.should_draw.push(push.to);
+        self.should_draw.push(push.from);
+    }
+    /// flip the lever at position, return its id and new state
+    pub fn pull_lever(&mut self, position: &Position) -> Option<(char, bool)> {
+        if let Some(ForegroundVariant::Lever { id, on }) = self.foregrounds.get_mut(position) {
+            *on = !*on;
+            self.should_draw.push(*position);
+            return Some((*id, *on));
+        }
+        None
+    }
+    pub fn toggle_barrier(&mut self, position: &Position) {
+        if self.barriers.remove(position) {
+            // barriers from map file leave rock behind
+            match self.covered.remove(position).unwrap_or(Some(ROCK.into())) {
+                Some(background) => {
+                    self.set_background(position, background);
+                }
+                None => {
+                    self.backgrounds.remove(position);
+                    self.waters.remove(position);
+                }
+            }
+        } else {
+            self.covered.insert(*position, self.backgrounds.get(position).cloned());
+            self.set_background(position, BARRIER.into());
+        }
+        self.should_draw.push(*position);
+    }
+    pub fn flood(&mut self, position: &Position) {
+        if !self.is_barrier(position) {
+            self.set_background(position, WATER.into());
+        }
+    }
+    pub fn drain(&mut self, position: &Position) {
+        if self.is_water(position) {
+            self.set_background(position, SAND.into());
+        }
+    }
+    /// flood sand at high tide and drain water at low tide, return true if it did
+    pub fn set_tide(&mut self, position: &Position, high: bool) -> bool {
+        match (self.backgrounds.get(position).map(String::as_str), high) {
+            (Some(SAND), true) => self.flood(position),
+            (Some(WATER), false) => self.drain(position),
+            _ => return false,
+        }
+        true
+    }
+    /// replace background at position, keeping barriers and waters in sync
+    pub fn set_background(&mut self, position: &Position, background: TileId) {
+        let tile = self.tileset.get(&background);
+        if tile.is_some_and(|tile| !tile.walkable) {
+            self.barriers.insert(*position);
+        } else {
+            self.barriers.remove(position);
+        }
+        if tile.is_some_and(|tile| tile.water) {
+            self.waters.insert(*position);
+        } else {
+            self.waters.remove(position);
+        }
+        self.backgrounds.insert(*position, background);
+        self.should_draw.push(*position);
+    }
+    pub fn plate(&self, position: &Position) -> Option<char> {
+        self.plates.get(position).cloned()
+    }
+    /// a puzzle is solved when every plate of it has a boulder on
+    pub fn is_puzzle_solved(&self, puzzle: char) -> bool {
+        self.plates
+            .iter()
+            .filter(|(_, p)| **p == puzzle)
+            .all(|(position, _)| self.is_boulder(position))
+    }
 }
 
 impl From<&RawGameMap> for MapLayers {
     fn from(raw_game_map: &RawGameMap) -> Self {
-        let mut map_layers = MapLayers::default();
-        for (position, map_object) in raw_game_map {
+        let mut map_layers = MapLayers {
+            fog: raw_game_map.fog,
+            tileset: raw_game_map.tileset.clone(),
+            ..Default::default()
+        };
+        for (position, map_object) in &raw_game_map.tiles {
             match map_object.into() {
                 MapObjectVariant::Foreground(f) => {
-                    map_layers.foregrounds.insert(*position, f);
-                }
-                MapObjectVariant::Background(b) => {
-                    if b.is_barrier() {
+                    if f.is_barrier() {
                         map_layers.barriers.insert(*position);
                     }
-
-                    if b.is_water() {
-                        map_layers.waters.insert(*position);
-                    }
-                    map_layers.backgrounds.insert(*position, b);
+                    map_layers.foregrounds.insert(*position, f);
+                }
+                MapObjectVariant::Background(id) => {
+                    map_layers.set_background(position, id);
+                }
+                MapObjectVariant::Plate(puzzle) => {
+                    map_layers.set_background(position, PLATE.into());
+                    map_layers.plates.insert(*position, puzzle);
                 }
             }
             map_layers.should_draw.push(*position);
         }
+        for npc in &raw_game_map.npcs {
+            map_layers.npcs.insert(npc.position, npc.icon);
+            map_layers.should_draw.push(npc.position);
+        }
+        for enemy in &raw_game_map.enemies {
+            map_layers.npcs.insert(enemy.position, enemy.icon);
+            map_layers.should_draw.push(enemy.position);
+        }
+        for condition in &raw_game_map.win {
+            if let WinCondition::Exit(position) = condition {
+                map_layers.exits.insert(*position);
+                map_layers.should_draw.push(*position);
+            }
+        }
         map_layers
     }
 }
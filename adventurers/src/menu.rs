@@ -0,0 +1,153 @@
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+use termgame::KeyCode;
+
+use crate::bindings::{Action, Bindings};
+use crate::save::save_path;
+use crate::utils::MessageType;
+
+const MARKER: &str = "> ";
+const NO_MARKER: &str = "  ";
+/// items shown at once, the screen only fits so many lines
+const PAGE_ITEMS: usize = 10;
+/// speeds to pick from in options, tiles per second
+const SPEEDS: [f32; 4] = [5.0, 10.0, 15.0, 20.0];
+
+#[derive(Clone)]
+pub enum TitleItem {
+    /// start the map from the beginning
+    New(PathBuf),
+    /// load the save file
+    Continue(PathBuf),
+    Records,
+    Quit,
+}
+
+#[derive(Clone, Copy)]
+pub enum PauseItem {
+    Resume,
+    Save,
+    Options,
+    Title,
+    Quit,
+}
+
+#[derive(Clone, Copy)]
+pub enum OptionItem {
+    Movement,
+    Speed,
+    /// colours of the map, going round the themes
+    Theme,
+    Back,
+}
+
+/// a list of items to pick one from
+pub struct Menu<T> {
+    pub title: String,
+    pub items: Vec<(String, T)>,
+    pub selected: usize,
+}
+
+impl<T: Clone> Menu<T> {
+    pub fn new(title: String, items: Vec<(String, T)>) -> Self {
+        Self {
+            title,
+            items,
+            selected: 0,
+        }
+    }
+
+    /// move the marker with up and down, return the item picked with Enter
+    pub fn on_key(&mut self, key_code: KeyCode, action: Option<Action>) -> Option<T> {
+        match (key_code, action) {
+            (_, Some(Action::MoveUp)) => {
+                self.selected = self.selected.checked_sub(1).unwrap_or(self.items.len() - 1);
+            }
+            (_, Some(Action::MoveDown)) => {
+                self.selected = (self.selected + 1) % self.items.len();
+            }
+            (KeyCode::Enter, _) => {
+                return self.items.get(self.selected).map(|(_, item)| item.clone());
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// one line per item of the page with the selected one, which is marked
+    pub fn text(&self) -> String {
+        let start = self.selected / PAGE_ITEMS * PAGE_ITEMS;
+        let end = (start + PAGE_ITEMS).min(self.items.len());
+        let lines = self.items[start..end].iter().enumerate().map(|(i, (label, _))| {
+            let marker = if start + i == self.selected { MARKER } else { NO_MARKER };
+            format!("{marker}{label}")
+        });
+        let mut lines = lines.collect::<Vec<_>>();
+        if self.items.len() > PAGE_ITEMS {
+            lines.push(format!("{}-{} of {}, up/down for more", start + 1, end, self.items.len()));
+        }
+        lines.join("\n")
+    }
+}
+
+#[allow(clippy::from_over_into)]
+impl<T: Clone> Into<MessageType> for &Menu<T> {
+    fn into(self) -> MessageType {
+        MessageType::Menu(self.title.clone(), self.text())
+    }
+}
+
+/// every map in the directory, with the saved game on it if any
+pub fn title_menu<P: AsRef<Path>>(maps_dir: P, title: String) -> Menu<TitleItem> {
+    let mut maps = match read_dir(maps_dir) {
+        Ok(entries) => entries.filter_map(|entry| Some(entry.ok()?.path())).collect(),
+        Err(_) => vec![],
+    };
+    maps.retain(|path| path.extension().is_some_and(|e| e == "ron"));
+    maps.sort();
+
+    let mut items = vec![];
+    for map in maps {
+        let name = map.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let save = save_path(&map);
+        if save.exists() {
+            items.push((format!("Continue: {name}"), TitleItem::Continue(save)));
+        }
+        items.push((format!("New game: {name}"), TitleItem::New(map)));
+    }
+    items.push(("Records".into(), TitleItem::Records));
+    items.push(("Quit".into(), TitleItem::Quit));
+    Menu::new(title, items)
+}
+
+pub fn pause_menu() -> Menu<PauseItem> {
+    Menu::new(
+        "Paused".into(),
+        vec![
+            ("Resume".into(), PauseItem::Resume),
+            ("Save".into(), PauseItem::Save),
+            ("Options".into(), PauseItem::Options),
+            ("Title screen".into(), PauseItem::Title),
+            ("Quit".into(), PauseItem::Quit),
+        ],
+    )
+}
+
+/// options with their current values
+pub fn options_menu(bindings: &Bindings, theme: &str) -> Menu<OptionItem> {
+    Menu::new(
+        "Options".into(),
+        vec![
+            (format!("Movement: {:?}", bindings.movement), OptionItem::Movement),
+            (format!("Speed: {} tiles per second", bindings.speed), OptionItem::Speed),
+            (format!("Theme: {theme}"), OptionItem::Theme),
+            ("Back".into(), OptionItem::Back),
+        ],
+    )
+}
+
+/// the speed after this one, going round
+pub fn next_speed(speed: f32) -> f32 {
+    SPEEDS.into_iter().find(|s| *s > speed).unwrap_or(SPEEDS[0])
+}
@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+use termgame::{GameColor, GameStyle, StyledCharacter};
+
+use crate::map::MapLayers;
+use crate::plain::to_ascii;
+use crate::tileset::TileId;
+use crate::utils::{ForegroundVariant, Position};
+
+const TARGET: char = '!';
+const SIGN: char = '⚑';
+
+/// how much of the view the map overlay covers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MapView {
+    #[default]
+    Hidden,
+    /// a quarter of the view, in the top right corner
+    Corner,
+    Full,
+}
+
+impl MapView {
+    pub fn next(self) -> Self {
+        match self {
+            MapView::Hidden => MapView::Corner,
+            MapView::Corner => MapView::Full,
+            MapView::Full => MapView::Hidden,
+        }
+    }
+
+    /// left, top, width and height in a view of the size
+    pub fn area(&self, width: i32, height: i32) -> Option<(i32, i32, i32, i32)> {
+        match self {
+            MapView::Hidden => None,
+            MapView::Corner => Some((width - width / 2, 0, width / 2, height / 2)),
+            MapView::Full => Some((0, 0, width, height)),
+        }
+    }
+}
+
+/// what a block shows over its dominant background, the most important first
+fn marker(
+    map_layers: &MapLayers,
+    position: &Position,
+    targets: &HashSet<Position>,
+) -> Option<(u8, char)> {
+    if targets.contains(position) {
+        return Some((2, TARGET));
+    }
+    match map_layers.foregrounds.get(position) {
+        Some(ForegroundVariant::Sign(_)) => Some((1, SIGN)),
+        Some(ForegroundVariant::Object(c)) => Some((0, *c)),
+        _ => None,
+    }
+}
+
+fn border(x: i32, y: i32, width: i32, height: i32) -> Option<char> {
+    match (x == 0, x == width - 1, y == 0, y == height - 1) {
+        (true, _, true, _) => Some('┌'),
+        (_, true, true, _) => Some('┐'),
+        (true, _, _, true) => Some('└'),
+        (_, true, _, true) => Some('┘'),
+        (true, ..) | (_, true, ..) => Some('│'),
+        (.., true, _) | (.., true) => Some('─'),
+        _ => None,
+    }
+}
+
+/// the known tiles scaled down to a width x height box with a border,
+/// one character per block of tiles, from the top left corner of the box
+pub fn render_minimap(
+    map_layers: &MapLayers,
+    player_icon: char,
+    targets: &HashSet<Position>,
+    (width, height): (i32, i32),
+) -> Vec<(Position, StyledCharacter)> {
+    let (inner_width, inner_height) = (width - 2, height - 2);
+    let tiles = map_layers.backgrounds.keys().chain(map_layers.foregrounds.keys());
+    let tiles = tiles.collect::<HashSet<_>>();
+    if inner_width < 1 || inner_height < 1 || tiles.is_empty() {
+        return vec![];
+    }
+    let left = tiles.iter().map(|p| p.0).min().unwrap_or_default();
+    let top = tiles.iter().map(|p| p.1).min().unwrap_or_default();
+    let right = tiles.iter().map(|p| p.0).max().unwrap_or_default();
+    let bottom = tiles.iter().map(|p| p.1).max().unwrap_or_default();
+    // tiles in a block, across and down
+    let block_width = (right - left + inner_width) / inner_width;
+    let block_height = (bottom - top + inner_height) / inner_height;
+    let block = |p: &Position| Position((p.0 - left) / block_width, (p.1 - top) / block_height);
+
+    let mut backgrounds: HashMap<Position, HashMap<&TileId, usize>> = HashMap::new();
+    let mut markers: HashMap<Position, (u8, char)> = HashMap::new();
+    let known = tiles.into_iter().filter(|p| !map_layers.fog || map_layers.explored.contains(p));
+    for position in known {
+        if let Some(background) = map_layers.backgrounds.get(position) {
+            let counts = backgrounds.entry(block(position)).or_default();
+            *counts.entry(background).or_default() += 1;
+        }
+        if let Some(marker) = marker(map_layers, position, targets) {
+            let shown = markers.entry(block(position)).or_insert(marker);
+            *shown = marker.max(*shown);
+        }
+    }
+    markers.insert(block(&map_layers.player), (u8::MAX, player_icon));
+
+    let mut cells = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let border = border(x, y, width, height);
+            let mut sc = StyledCharacter::new(border.unwrap_or(' '));
+            if border.is_none() {
+                let position = Position(x - 1, y - 1);
+                let dominant = backgrounds.get(&position).and_then(|counts| {
+                    // ties go the same way every tick
+                    let dominant = counts.iter().max_by_key(|(b, n)| (**n, **b));
+                    dominant.and_then(|(background, _)| map_layers.tileset.get(background))
+                });
+                let render = map_layers.render;
+                match (markers.get(&position), dominant) {
+                    (Some((_, c)), _) => sc.c = *c,
+                    (None, Some(tile)) if render.is_plain() => sc.c = tile.glyph_in(render),
+                    _ => {}
+                }
+                let color = match dominant {
+                    Some(tile) => tile.bg.into(),
+                    None => GameColor::Black,
+                };
+                if !render.monochrome {
+                    sc.style = Some(GameStyle::new().background_color(Some(color)));
+                }
+            }
+            if map_layers.render.ascii {
+                sc.c = to_ascii(sc.c);
+            }
+            cells.push((Position(x, y), sc));
+        }
+    }
+    cells
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::Position;
+
+/// which moves the player may take
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementRule {
+    /// no diagonal moves, the horizontal part of one is taken
+    FourWay,
+    /// diagonal moves only when both tiles beside the way are free
+    #[default]
+    NoCornerCutting,
+    /// diagonal moves may slip between two barriers
+    Free,
+}
+
+impl MovementRule {
+    /// the rule after this one, going round
+    pub fn cycle(&self) -> MovementRule {
+        match self {
+            MovementRule::FourWay => MovementRule::NoCornerCutting,
+            MovementRule::NoCornerCutting => MovementRule::Free,
+            MovementRule::Free => MovementRule::FourWay,
+        }
+    }
+
+    /// the move actually taken for the wanted one, None if it is not allowed
+    pub fn resolve<F: Fn(&Position) -> bool>(
+        &self,
+        from: &Position,
+        move_by: Position,
+        is_barrier: F,
+    ) -> Option<Position> {
+        let Position(x, y) = move_by;
+        if x == 0 || y == 0 {
+            return Some(move_by);
+        }
+        match self {
+            MovementRule::FourWay => Some(Position(x, 0)),
+            MovementRule::NoCornerCutting => {
+                let beside = [*from + Position(x, 0), *from + Position(0, y)];
+                if beside.iter().any(is_barrier) {
+                    return None;
+                }
+                Some(move_by)
+            }
+            MovementRule::Free => Some(move_by),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: [MovementRule; 3] =
+        [MovementRule::FourWay, MovementRule::NoCornerCutting, MovementRule::Free];
+
+    /// a barrier right of the origin, so (1, 1) is reached by cutting its corner
+    fn wall(p: &Position) -> bool {
+        *p == Position(1, 0)
+    }
+
+    #[test]
+    fn straight_moves_are_kept() {
+        let origin = Position(0, 0);
+        for rule in RULES {
+            assert_eq!(rule.resolve(&origin, Position(1, 0), wall), Some(Position(1, 0)));
+            assert_eq!(rule.resolve(&origin, Position(0, -1), wall), Some(Position(0, -1)));
+        }
+    }
+
+    #[test]
+    fn four_way_takes_horizontal_part() {
+        let rule = MovementRule::FourWay;
+        let resolved = rule.resolve(&Position(0, 0), Position(-1, 1), |_| false);
+        assert_eq!(resolved, Some(Position(-1, 0)));
+    }
+
+    #[test]
+    fn no_corner_cutting() {
+        let rule = MovementRule::NoCornerCutting;
+        assert_eq!(rule.resolve(&Position(0, 0), Position(1, 1), wall), None);
+        assert_eq!(rule.resolve(&Position(0, 0), Position(1, -1), wall), None);
+        assert_eq!(rule.resolve(&Position(0, 0), Position(-1, 1), wall), Some(Position(-1, 1)));
+        assert_eq!(rule.resolve(&Position(1, 1), Position(-1, -1), wall), None);
+    }
+
+    #[test]
+    fn free_cuts_corners() {
+        let rule = MovementRule::Free;
+        assert_eq!(rule.resolve(&Position(0, 0), Position(1, 1), wall), Some(Position(1, 1)));
+    }
+
+    #[test]
+    fn cycle_goes_round() {
+        for rule in RULES {
+            assert_eq!(rule.cycle().cycle().cycle(), rule);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::mem::discriminant;
+
+use crate::utils::MessageType;
+
+/// ticks a toast is shown unless told otherwise
+const TOAST_TICKS: i32 = 40;
+/// toasts kept waiting, the oldest of the lowest priority is dropped
+const MAX_TOASTS: usize = 8;
+
+/// how important a toast is, higher ones are shown over panels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Normal,
+    High,
+}
+
+/// where a message goes in the notifications
+enum Layer {
+    /// shown until cleared, over everything else
+    Modal,
+    /// opened and closed by the player
+    Panel,
+    /// shown for the given ticks, then gone
+    Toast(Priority, i32),
+}
+
+impl From<&MessageType> for Layer {
+    fn from(message: &MessageType) -> Self {
+        use MessageType::*;
+        match message {
+            Death(_) | Victory(_) | Menu(..) | Dialogue(..) => Layer::Modal,
+            Debug(_) | Help(_) | Bag(_) | Quest(_) | Timer(_) | Log(_) | None => Layer::Panel,
+            Hurt(..) => Layer::Toast(Priority::High, TOAST_TICKS / 2),
+            Notice(_) => Layer::Toast(Priority::High, TOAST_TICKS * 2),
+            Sign(_) => Layer::Toast(Priority::Normal, TOAST_TICKS * 3),
+            Achievement(..) => Layer::Toast(Priority::Normal, TOAST_TICKS * 3 / 2),
+            Pickup(_) | Locked(_) | Unlock(_) | Puzzle(_) | Defeat(_) | Walk(_) | Split(_)
+            | Progress(_) | Equip(_) => Layer::Toast(Priority::Normal, TOAST_TICKS),
+        }
+    }
+}
+
+struct Toast {
+    message: MessageType,
+    priority: Priority,
+    /// ticks left to show it
+    ticks: i32,
+}
+
+/// what the message box shows: a modal message, else the most important
+/// toast, with panels above normal toasts
+#[derive(Default)]
+pub struct Notifications {
+    modal: Option<MessageType>,
+    panel: MessageType,
+    /// oldest first
+    toasts: Vec<Toast>,
+}
+
+impl Notifications {
+    /// show the message in its layer
+    pub fn push(&mut self, message: MessageType) {
+        match Layer::from(&message) {
+            Layer::Modal => self.modal = Some(message),
+            Layer::Panel => self.panel = message,
+            Layer::Toast(priority, ticks) => {
+                if self.toasts.len() >= MAX_TOASTS {
+                    let lowest = self.toasts.iter().map(|t| t.priority).min();
+                    let oldest = self.toasts.iter().position(|t| Some(t.priority) == lowest);
+                    self.toasts.remove(oldest.unwrap_or_default());
+                }
+                self.toasts.push(Toast {
+                    message,
+                    priority,
+                    ticks,
+                });
+            }
+        }
+    }
+
+    /// open the panel, or close it if one of its kind is open
+    pub fn toggle(&mut self, panel: MessageType) {
+        if discriminant(&self.panel) == discriminant(&panel) {
+            self.panel = MessageType::None;
+        } else {
+            self.push(panel);
+        }
+    }
+
+    /// replace the open panel with the message if they are of a kind
+    pub fn refresh(&mut self, panel: MessageType) {
+        if discriminant(&self.panel) == discriminant(&panel) {
+            self.panel = panel;
+        }
+    }
+
+    pub fn close_panel(&mut self) {
+        self.panel = MessageType::None;
+    }
+
+    pub fn clear_modal(&mut self) {
+        self.modal = None;
+    }
+
+    /// index of the toast on screen, if nothing covers it
+    fn shown_toast(&self) -> Option<usize> {
+        if self.modal.is_some() {
+            return None;
+        }
+        // the earliest of the most important ones
+        let (index, toast) = self
+            .toasts
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, toast)| toast.priority)?;
+        match (toast.priority, &self.panel) {
+            (Priority::High, _) | (_, MessageType::None) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// count down the toast on screen, dropping it once expired
+    pub fn tick(&mut self) {
+        if let Some(index) = self.shown_toast() {
+            self.toasts[index].ticks -= 1;
+            if self.toasts[index].ticks <= 0 {
+                self.toasts.remove(index);
+            }
+        }
+    }
+
+    pub fn current(&self) -> MessageType {
+        if let Some(modal) = &self.modal {
+            return modal.clone();
+        }
+        match self.shown_toast() {
+            Some(index) => self.toasts[index].message.clone(),
+            None => self.panel.clone(),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+
+use crate::plain::NPC_ASCII;
+use crate::quest::RawQuest;
+use crate::utils::{Item, Position};
+
+/// width of the message area, without borders
+const LINE_WIDTH: usize = 76;
+/// lines of dialogue text shown at once
+const PAGE_LINES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Npc {
+    pub name: String,
+    pub icon: char,
+    /// drawn instead of the icon without unicode
+    #[serde(default)]
+    pub ascii: Option<char>,
+    pub position: Position,
+    /// wander around the start position within the distance
+    #[serde(default)]
+    pub wander: Option<i32>,
+    /// dialogue begins with the first node
+    pub dialogue: Vec<DialogueNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DialogueNode {
+    pub text: String,
+    /// dialogue ends after this node if there is no choice
+    #[serde(default)]
+    pub choices: Vec<Choice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Choice {
+    pub text: String,
+    /// index of the next node, dialogue ends if None
+    #[serde(default)]
+    pub next: Option<usize>,
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+    /// only offered when the flag is set
+    #[serde(default)]
+    pub requires: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DialogueEffect {
+    SetFlag(String),
+    Give(Item),
+    StartQuest(RawQuest),
+    /// raise the oxygen the player holds at most
+    MaxOxygen(i32),
+}
+
+/// an ongoing conversation
+pub struct Dialogue {
+    pub npc: usize,
+    pub node: usize,
+    pub page: usize,
+}
+
+impl Dialogue {
+    pub fn new(npc: usize) -> Self {
+        Self {
+            npc,
+            node: 0,
+            page: 0,
+        }
+    }
+
+    /// return false if there is no more page to turn to
+    pub fn next_page(&mut self, npc: &Npc) -> bool {
+        if self.page + 1 < npc.pages(self.node).len() {
+            self.page += 1;
+            return true;
+        }
+        false
+    }
+
+    pub fn is_last_page(&self, npc: &Npc) -> bool {
+        self.page + 1 >= npc.pages(self.node).len()
+    }
+
+    pub fn text<F: Fn(&str) -> bool>(&self, npc: &Npc, has_flag: F) -> String {
+        let pages = npc.pages(self.node);
+        let mut text = pages[self.page].clone();
+        if !self.is_last_page(npc) {
+            text += &format!("\n[Enter] ({}/{})", self.page + 1, pages.len());
+            return text;
+        }
+        let choices = npc.choices(self.node, has_flag);
+        if choices.is_empty() {
+            text += "\n[Enter] end";
+        }
+        for (i, choice) in choices.iter().enumerate() {
+            text += &format!("\n{}. {}", i + 1, choice.text);
+        }
+        text
+    }
+}
+
+impl Npc {
+    /// icon drawn without unicode
+    pub fn ascii_icon(&self) -> char {
+        match self.ascii {
+            Some(ascii) => ascii,
+            None if self.icon.is_ascii() => self.icon,
+            None => NPC_ASCII,
+        }
+    }
+
+    /// text of the node, wrapped and split into pages
+    pub fn pages(&self, node: usize) -> Vec<String> {
+        let lines = wrap(&self.dialogue[node].text, LINE_WIDTH);
+        let pages = lines.chunks(PAGE_LINES).map(|page| page.join("\n")).collect::<Vec<_>>();
+        // a blank text still makes a page to show the choices on
+        match pages.is_empty() {
+            true => vec![String::new()],
+            false => pages,
+        }
+    }
+
+    /// what keeps the dialogue from being followed, if anything
+    pub fn dialogue_error(&self) -> Option<String> {
+        if self.dialogue.is_empty() {
+            return Some(format!("{} has no dialogue", self.name));
+        }
+        let nexts = self.dialogue.iter().flat_map(|node| &node.choices);
+        let mut nexts = nexts.filter_map(|choice| choice.next);
+        let missing = nexts.find(|next| *next >= self.dialogue.len())?;
+        Some(format!("{} has no dialogue node {missing}", self.name))
+    }
+
+    /// quests the dialogue may start
+    pub fn quests(&self) -> impl Iterator<Item = &RawQuest> {
+        let choices = self.dialogue.iter().flat_map(|node| &node.choices);
+        let effects = choices.flat_map(|choice| &choice.effects);
+        effects.filter_map(|effect| match effect {
+            DialogueEffect::StartQuest(quest) => Some(quest),
+            _ => None,
+        })
+    }
+
+    /// choices offered at the node
+    pub fn choices<F: Fn(&str) -> bool>(&self, node: usize, has_flag: F) -> Vec<&Choice> {
+        self.dialogue[node]
+            .choices
+            .iter()
+            .filter(|choice| choice.requires.as_ref().is_none_or(|flag| has_flag(flag)))
+            .collect()
+    }
+}
+
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + word.chars().count() + 1 > width {
+                lines.push(line);
+                line = String::new();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += word;
+        }
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,196 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use crate::utils::Position;
+
+/// give up searching after visiting this many tiles
+const SEARCH_LIMIT: usize = 4096;
+
+const NEIGHBOURS: [Position; 4] = [
+    Position(0, -1),
+    Position(0, 1),
+    Position(-1, 0),
+    Position(1, 0),
+];
+
+fn distance(a: &Position, b: &Position) -> i32 {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs()
+}
+
+/// A* search over 4-way moves, return the positions after `from` up to `to`.
+/// `to` itself need not be walkable, so one can path towards a blocked target.
+pub fn find_path<F: Fn(&Position) -> bool>(
+    from: Position,
+    to: Position,
+    is_walkable: F,
+) -> Option<Vec<Position>> {
+    if from == to {
+        return Some(vec![]);
+    }
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Position, Position> = HashMap::new();
+    let mut cost: HashMap<Position, i32> = HashMap::new();
+
+    cost.insert(from, 0);
+    open.push(Reverse((distance(&from, &to), 0, from.0, from.1)));
+
+    while let Some(Reverse((_, current_cost, x, y))) = open.pop() {
+        let current = Position(x, y);
+        if current == to {
+            let mut path = vec![current];
+            let mut position = current;
+            while let Some(previous) = came_from.get(&position) {
+                if *previous == from {
+                    break;
+                }
+                path.push(*previous);
+                position = *previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if cost.len() > SEARCH_LIMIT {
+            return None;
+        }
+        if current_cost > cost[&current] {
+            continue;
+        }
+        for move_by in NEIGHBOURS.iter() {
+            let next = current + *move_by;
+            if next != to && !is_walkable(&next) {
+                continue;
+            }
+            let next_cost = current_cost + 1;
+            if cost.get(&next).is_some_and(|c| *c <= next_cost) {
+                continue;
+            }
+            cost.insert(next, next_cost);
+            came_from.insert(next, current);
+            open.push(Reverse((next_cost + distance(&next, &to), next_cost, next.0, next.1)));
+        }
+    }
+    None
+}
+
+/// breadth first search for a path the player survives: oxygen is `oxygen` at start
+/// and `oxygen_at` a tile given the oxygen before, a path runs out of it at 0.
+pub fn find_safe_path<F: Fn(&Position) -> bool, O: Fn(&Position, i32) -> i32>(
+    from: Position,
+    to: Position,
+    oxygen: i32,
+    is_walkable: F,
+    oxygen_at: O,
+) -> Option<Vec<Position>> {
+    let mut open = VecDeque::new();
+    let mut came_from: HashMap<(Position, i32), (Position, i32)> = HashMap::new();
+
+    open.push_back((from, oxygen));
+    while let Some(current) = open.pop_front() {
+        if current.0 == to {
+            let mut path = vec![];
+            let mut state = current;
+            while state != (from, oxygen) {
+                path.push(state.0);
+                state = came_from[&state];
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if came_from.len() > SEARCH_LIMIT * 4 {
+            return None;
+        }
+        for move_by in NEIGHBOURS.iter() {
+            let next = current.0 + *move_by;
+            if !is_walkable(&next) {
+                continue;
+            }
+            let next_oxygen = oxygen_at(&next, current.1);
+            let state = (next, next_oxygen);
+            if next_oxygen <= 0 || state == (from, oxygen) || came_from.contains_key(&state) {
+                continue;
+            }
+            came_from.insert(state, current);
+            open.push_back(state);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a 7 by 3 pool of water with one air tile at (3, 1), oxygen refills to 5 there
+    fn pool(p: &Position) -> bool {
+        (0..=6).contains(&p.0) && (0..=2).contains(&p.1)
+    }
+
+    fn pool_oxygen(p: &Position, oxygen: i32) -> i32 {
+        if *p == Position(3, 1) {
+            5
+        } else {
+            oxygen - 1
+        }
+    }
+
+    /// a wall ring around the origin on an endless plane
+    fn outside_ring(p: &Position) -> bool {
+        p.0.abs().max(p.1.abs()) != 1
+    }
+
+    #[test]
+    fn find_path_straight() {
+        let path = find_path(Position(0, 0), Position(3, 0), |_| true);
+        assert_eq!(path, Some(vec![Position(1, 0), Position(2, 0), Position(3, 0)]));
+        assert_eq!(find_path(Position(2, 2), Position(2, 2), |_| false), Some(vec![]));
+    }
+
+    #[test]
+    fn find_path_around_wall() {
+        let path = find_path(Position(0, 0), Position(2, 0), |p| p.0 != 1 || p.1 == 2).unwrap();
+        assert_eq!(path.len(), 6);
+        assert!(path.contains(&Position(1, 2)));
+        assert_eq!(path.last(), Some(&Position(2, 0)));
+    }
+
+    #[test]
+    fn find_path_to_blocked_target() {
+        let path = find_path(Position(0, 0), Position(2, 0), |p| *p != Position(2, 0));
+        assert_eq!(path, Some(vec![Position(1, 0), Position(2, 0)]));
+    }
+
+    #[test]
+    fn find_path_unreachable() {
+        assert_eq!(find_path(Position(0, 0), Position(9, 0), pool), None);
+    }
+
+    #[test]
+    fn find_path_search_limit() {
+        assert_eq!(find_path(Position(5, 5), Position(0, 0), outside_ring), None);
+    }
+
+    #[test]
+    fn find_safe_path_runs_out_of_oxygen() {
+        let no_air = |_: &Position, oxygen: i32| oxygen - 1;
+        let path = find_safe_path(Position(0, 0), Position(3, 0), 4, |_| true, no_air);
+        assert_eq!(path, Some(vec![Position(1, 0), Position(2, 0), Position(3, 0)]));
+        assert_eq!(find_safe_path(Position(0, 0), Position(4, 0), 4, |_| true, no_air), None);
+    }
+
+    #[test]
+    fn find_safe_path_through_air() {
+        let path = find_safe_path(Position(0, 0), Position(6, 0), 5, pool, pool_oxygen).unwrap();
+        assert_eq!(path.len(), 8);
+        assert!(path.contains(&Position(3, 1)));
+        assert_eq!(path.last(), Some(&Position(6, 0)));
+        let row = |p: &Position| pool(p) && p.1 == 0;
+        assert_eq!(find_safe_path(Position(0, 0), Position(6, 0), 5, row, pool_oxygen), None);
+    }
+
+    #[test]
+    fn find_safe_path_unreachable() {
+        let full = |_: &Position, oxygen: i32| oxygen;
+        assert_eq!(find_safe_path(Position(0, 0), Position(9, 0), 5, pool, full), None);
+        assert_eq!(find_safe_path(Position(5, 5), Position(0, 0), 5, outside_ring, full), None);
+    }
+}
@@ -0,0 +1,93 @@
+use std::env;
+use std::error::Error;
+
+/// how plainly the map is drawn, for terminals without unicode or colours
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderMode {
+    /// only ASCII characters
+    pub ascii: bool,
+    /// no colours, tiles seen before are drawn dim
+    pub monochrome: bool,
+}
+
+impl RenderMode {
+    /// what the terminal is likely to show, going by the environment
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        // the first locale variable set decides the character set
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().map(var).find(|l| !l.is_empty());
+        let utf8 = locale.is_none_or(|l| {
+            let l = l.to_lowercase();
+            l.contains("utf-8") || l.contains("utf8")
+        });
+        RenderMode {
+            ascii: !utf8 || term == "dumb" || term == "linux",
+            monochrome: term == "dumb" || !var("NO_COLOR").is_empty(),
+        }
+    }
+
+    /// the mode with the command line flags on top
+    pub fn with_args<I>(mut self, args: I) -> Result<Self, Box<dyn Error>>
+    where
+        I: Iterator<Item = String>,
+    {
+        for arg in args {
+            match arg.as_str() {
+                "--ascii" => self.ascii = true,
+                "--monochrome" => self.monochrome = true,
+                "--plain" => {
+                    self.ascii = true;
+                    self.monochrome = true;
+                }
+                "--fancy" => {
+                    self.ascii = false;
+                    self.monochrome = false;
+                }
+                _ => {
+                    let flags = "--ascii, --monochrome, --plain or --fancy";
+                    return Err(format!("unknown option '{arg}', expected {flags}").into());
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// tiles are told apart by their glyphs rather than colours
+    pub fn is_plain(&self) -> bool {
+        self.ascii || self.monochrome
+    }
+}
+
+/// drawn without unicode for characters of maps with no ASCII icon of their own,
+/// friendly ones apart from hostile ones
+pub const NPC_ASCII: char = '&';
+pub const ENEMY_ASCII: char = 'E';
+
+/// an ASCII character standing in for the one drawn
+pub fn to_ascii(c: char) -> char {
+    match c {
+        _ if c.is_ascii() => c,
+        // player, sign, door, boulder, levers, cursor and exit
+        '☻' => '@',
+        '⚑' => '?',
+        '▯' => '+',
+        '●' => '0',
+        '╱' => '/',
+        '╲' => '\\',
+        '✛' => 'X',
+        '⚐' => '%',
+        '↑' => '^',
+        '↓' => 'v',
+        '←' => '<',
+        '→' => '>',
+        '°' => 'o',
+        '□' => '_',
+        // borders of the map overlay
+        '┌' | '┐' | '└' | '┘' => '+',
+        '│' => '|',
+        '─' => '-',
+        // characters of maps, like npcs
+        _ => '&',
+    }
+}
@@ -1,15 +1,48 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::equipment::{Equipment, Slot};
 use crate::map::MapLayers;
+use crate::tileset::Tile;
 use crate::utils::Position;
 
 const PLAYER_ICON: char = '☻';
 const PLAYER_INIT_OXYGEN: i32 = 10;
+/// ticks on land to breathe a unit of oxygen back in
+const OXYGEN_REFILL_TICKS: i32 = 10;
+const PLAYER_INIT_HEALTH: i32 = 3;
+/// ticks the player cannot be hurt again after being hurt
+const PLAYER_HURT_COOLDOWN: i32 = 10;
+/// tiles per second
+pub const PLAYER_SPEED: f32 = 10.0;
+
+fn default_max_oxygen() -> i32 {
+    PLAYER_INIT_OXYGEN
+}
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Player {
+    #[serde(skip)]
     pub update_draw: bool,
     pub icon: char,
     pub position: Position,
     pub bag: Vec<char>,
     pub oxygen: i32,
+    /// oxygen held at most without equipment, raised by upgrades
+    #[serde(default = "default_max_oxygen")]
+    pub max_oxygen: i32,
+    #[serde(default)]
+    pub equipment: BTreeMap<Slot, Equipment>,
+    pub health: i32,
+    /// ticks left before player can be hurt again
+    pub invulnerable: i32,
+    /// tiles per second on plain ground
+    pub speed: f32,
+    /// way walked towards the next tile, None when standing still
+    #[serde(skip)]
+    pub stride: Option<f32>,
+    #[serde(skip)]
     pub previous_position: Option<Position>,
 }
 
@@ -19,12 +52,94 @@ impl Player {
         self.update_draw = true;
     }
 
+    /// use up oxygen stepping into water, air bubbles fill it up
     pub fn interact_background(&mut self, map: &MapLayers) {
         if map.is_water(&self.position) {
-            self.oxygen -= 1;
+            self.oxygen = self.oxygen_at(map, &self.position, self.oxygen);
+        }
+    }
+
+    /// breathe in the tick, filling a unit of oxygen up on land every so many ticks
+    pub fn breathe(&mut self, map: &MapLayers, frame: i32) {
+        if map.is_water(&self.position) {
             return;
         }
-        self.oxygen = PLAYER_INIT_OXYGEN;
+        self.oxygen = self.oxygen_at(map, &self.position, self.oxygen);
+        if frame % OXYGEN_REFILL_TICKS == 0 {
+            self.oxygen = (self.oxygen + 1).min(self.oxygen_capacity());
+        }
+    }
+
+    /// oxygen left after moving onto the tile with the given oxygen,
+    /// land keeps it until breathed in over time
+    pub fn oxygen_at(&self, map: &MapLayers, position: &Position, oxygen: i32) -> i32 {
+        match map.tile(position) {
+            Some(tile) if tile.air => self.oxygen_capacity(),
+            _ if map.is_water(position) => oxygen - 1,
+            _ => oxygen,
+        }
+    }
+
+    /// oxygen held at most, with what is worn
+    pub fn oxygen_capacity(&self) -> i32 {
+        self.max_oxygen + self.equipment.values().map(|e| e.oxygen).sum::<i32>()
+    }
+
+    /// put on equipment, taking off what was worn in its slot
+    pub fn equip(&mut self, equipment: Equipment) {
+        self.equipment.insert(equipment.slot, equipment);
+    }
+
+    /// how many tiles of plain ground it takes to walk onto the tile
+    pub fn movement_cost(&self, tile: Option<&Tile>) -> f32 {
+        let cost = tile.map_or(1.0, |tile| tile.cost);
+        match tile {
+            Some(tile) if tile.water => self.equipment.values().fold(cost, |cost, e| cost * e.swim),
+            _ => cost,
+        }
+    }
+
+    /// oxygen, health and equipment, shown with the bag
+    pub fn stats(&self) -> String {
+        let worn = self.equipment.values().map(|e| format!("{} ({:?})", e.name, e.slot));
+        let worn = worn.collect::<Vec<_>>();
+        format!(
+            "Oxygen: {}/{}   Health: {}   Equipped: {}",
+            self.oxygen,
+            self.oxygen_capacity(),
+            self.health,
+            if worn.is_empty() { "-".into() } else { worn.join(", ") }
+        )
+    }
+
+    /// walk for a tick towards a tile of the cost, return true once it is reached.
+    /// the first step from standing still is immediate, `step` spends the stride.
+    pub fn walk(&mut self, cost: f32, seconds: f32) -> bool {
+        let stride = match self.stride {
+            Some(stride) => stride + self.speed * seconds,
+            None => cost,
+        };
+        self.stride = Some(stride);
+        stride >= cost
+    }
+
+    /// spend the stride of stepping onto, or into, a tile of the cost
+    pub fn step(&mut self, cost: f32) {
+        self.stride = self.stride.map(|stride| stride - cost);
+    }
+
+    pub fn stand(&mut self) {
+        self.stride = None;
+    }
+
+    /// return false if player is still recovering from last hurt
+    pub fn hurt(&mut self, damage: i32) -> bool {
+        if self.invulnerable > 0 {
+            return false;
+        }
+        self.health -= damage;
+        self.invulnerable = PLAYER_HURT_COOLDOWN;
+        true
     }
 }
 
@@ -37,6 +152,12 @@ impl Default for Player {
             bag: Default::default(),
             previous_position: None,
             oxygen: PLAYER_INIT_OXYGEN,
+            max_oxygen: PLAYER_INIT_OXYGEN,
+            equipment: Default::default(),
+            health: PLAYER_INIT_HEALTH,
+            invulnerable: 0,
+            speed: PLAYER_SPEED,
+            stride: None,
         }
     }
 }
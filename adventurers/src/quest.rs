@@ -1,17 +1,128 @@
+use std::collections::HashSet;
 use std::fmt::Display;
 
 pub use adventurers_quest::{Quest, QuestProgress, QuestStatus, Reset};
+use serde::{Deserialize, Serialize};
 
-use crate::utils::{Event, BackgroundVariant, Item};
+use crate::clock::TimeOfDay;
+use crate::map::MapLayers;
+use crate::tileset::{TileId, WATER};
+use crate::utils::{Event, ForegroundVariant, Item, Position};
+
+/// quest declared in map files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RawQuest {
+    /// walk on a number of tiles of the id in a row
+    Step(TileId, usize),
+    Pickup(Item, usize),
+    Puzzle(char),
+    Flag(String),
+    /// defeat number of enemies, of the name if given
+    Defeat(Option<String>, usize),
+    Evade(Option<String>, usize),
+    /// see the given percent of the map
+    Explore(usize),
+    /// the quest only goes on at the time of day
+    During(TimeOfDay, Box<RawQuest>),
+    Compound(Vec<RawQuest>),
+}
+
+/// how a map is won, every condition of the map must hold
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WinCondition {
+    /// every quest is completed
+    Quests,
+    /// player stands on the tile
+    Exit(Position),
+    /// no object is left lying on the map
+    Objects,
+}
+
+/// quests of maps that declare none
+pub fn default_quests() -> Vec<RawQuest> {
+    vec![RawQuest::Compound(vec![
+        RawQuest::Step(WATER.into(), 5),
+        RawQuest::Pickup('x', 3),
+    ])]
+}
+
+impl RawQuest {
+    /// ids of the tiles the quest names
+    pub fn tiles(&self) -> Vec<&TileId> {
+        match self {
+            RawQuest::Step(id, _) => vec![id],
+            RawQuest::During(_, quest) => quest.tiles(),
+            RawQuest::Compound(quests) => quests.iter().flat_map(|q| q.tiles()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// stage a compound quest went on or the quest was completed at,
+    /// none for progress within a stage
+    pub fn milestone(&self, before: QuestStatus, after: QuestStatus) -> Option<usize> {
+        match (self, before, after) {
+            (_, QuestStatus::Completed, _) => None,
+            (RawQuest::Compound(_), QuestStatus::Pending(stage), _) if before != after => {
+                Some(stage)
+            }
+            (_, QuestStatus::Pending(stage), QuestStatus::Completed) => Some(stage),
+            _ => None,
+        }
+    }
+
+    /// tiles of the map the quest leads to, marked on the map overlay
+    pub fn targets(&self, map_layers: &MapLayers) -> HashSet<Position> {
+        match self {
+            RawQuest::Pickup(item, _) => {
+                let objects = map_layers.foregrounds.iter();
+                let objects = objects.filter(|(_, f)| f == &&ForegroundVariant::Object(*item));
+                objects.map(|(position, _)| *position).collect()
+            }
+            RawQuest::Puzzle(puzzle) => {
+                let plates = map_layers.plates.iter();
+                let plates = plates.filter(|(_, p)| *p == puzzle);
+                plates.map(|(position, _)| *position).collect()
+            }
+            RawQuest::During(_, quest) => quest.targets(map_layers),
+            RawQuest::Compound(sub_quests) => {
+                sub_quests.iter().flat_map(|q| q.targets(map_layers)).collect()
+            }
+            _ => HashSet::new(),
+        }
+    }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<Box<dyn Quest<Event>>> for &RawQuest {
+    fn into(self) -> Box<dyn Quest<Event>> {
+        match self {
+            RawQuest::Step(background, steps) => Box::new(StepQuest::new(background.clone(), *steps)),
+            RawQuest::Pickup(item, number) => Box::new(PickupQuest::new(*item, *number)),
+            RawQuest::Puzzle(puzzle) => Box::new(PuzzleQuest::new(*puzzle)),
+            RawQuest::Flag(flag) => Box::new(FlagQuest::new(flag.clone())),
+            RawQuest::Defeat(name, number) => {
+                Box::new(EnemyQuest::new(Encounter::Defeat, name.clone(), *number))
+            }
+            RawQuest::Evade(name, number) => {
+                Box::new(EnemyQuest::new(Encounter::Evade, name.clone(), *number))
+            }
+            RawQuest::Explore(percent) => Box::new(ExploreQuest::new(*percent)),
+            RawQuest::During(time, quest) => Box::new(TimeQuest::new(*time, quest.as_ref().into())),
+            RawQuest::Compound(sub_quests) => Box::new(CompoundQuest::new(
+                sub_quests.iter().map(|q| q.into()).collect(),
+            )),
+        }
+    }
+}
 
 
 pub struct StepQuest {
-    background: BackgroundVariant,
+    background: TileId,
     progress: QuestProgress,
 }
 
 impl StepQuest {
-    pub fn new(background: BackgroundVariant, steps: usize) -> Self {
+    pub fn new(background: TileId, steps: usize) -> Self {
         Self {
             background,
             progress: QuestProgress::new(steps),
@@ -37,14 +148,15 @@ impl Reset for StepQuest {
 }
 
 impl Quest<Event> for StepQuest {
+    #[allow(clippy::single_match)]
     fn update(&mut self, event: &Event) {
         if self.is_completed() {
             return;
         }
         match event {
             Event::MoveTo(_, b) => {
-                if b == &Some(self.background) {
-                    self.progress.next();
+                if b.as_ref() == Some(&self.background) {
+                    self.progress.advance();
                 } else {
                     self.progress.reset();
                 }
@@ -94,6 +206,7 @@ impl Reset for PickupQuest {
 }
 
 impl Quest<Event> for PickupQuest {
+    #[allow(clippy::single_match, clippy::collapsible_match)]
     fn update(&mut self, event: &Event) {
         if self.is_completed() {
             return;
@@ -101,7 +214,7 @@ impl Quest<Event> for PickupQuest {
         match event {
             Event::Pickup(item) => {
                 if item == &self.item {
-                    self.progress.next();
+                    self.progress.advance();
                 }
             }
             _ => {}
@@ -151,13 +264,14 @@ impl Reset for CompoundQuest {
 }
 
 impl Quest<Event> for CompoundQuest {
+    #[allow(clippy::single_match)]
     fn update(&mut self, event: &Event) {
         match self.progress.progress() {
             Some((current, _)) => {
                 let sub_quest = &mut self.sub_quests[current - 1];
                 sub_quest.update(event);
                 if sub_quest.is_completed() {
-                    self.progress.next();
+                    self.progress.advance();
                 }
             }
             None => {}
@@ -172,3 +286,397 @@ impl Quest<Event> for CompoundQuest {
         self.progress.is_completed()
     }
 }
+
+pub struct PuzzleQuest {
+    puzzle: char,
+    progress: QuestProgress,
+}
+
+impl PuzzleQuest {
+    pub fn new(puzzle: char) -> Self {
+        Self {
+            puzzle,
+            progress: QuestProgress::new(1),
+        }
+    }
+}
+
+impl Display for PuzzleQuest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "solve puzzle '{}'.", self.puzzle)?;
+        if self.is_completed() {
+            write!(f, "(Completed)")?;
+        }
+        Ok(())
+    }
+}
+
+impl Reset for PuzzleQuest {
+    fn reset(&mut self) {
+        self.progress.reset();
+    }
+}
+
+impl Quest<Event> for PuzzleQuest {
+    fn update(&mut self, event: &Event) {
+        match event {
+            Event::PuzzleSolved(puzzle) if puzzle == &self.puzzle => {
+                self.progress.advance();
+            }
+            _ => {}
+        }
+    }
+
+    fn status(&self) -> QuestStatus {
+        self.progress.status
+    }
+
+    fn is_completed(&self) -> bool {
+        self.progress.is_completed()
+    }
+}
+
+pub struct ExploreQuest {
+    percent: usize,
+    /// percent of the map seen so far
+    explored: usize,
+    progress: QuestProgress,
+}
+
+impl ExploreQuest {
+    pub fn new(percent: usize) -> Self {
+        Self {
+            percent,
+            explored: 0,
+            progress: QuestProgress::new(1),
+        }
+    }
+}
+
+impl Display for ExploreQuest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "explore {}% of the map.", self.percent)?;
+        match self.is_completed() {
+            true => write!(f, "(Completed)")?,
+            false => write!(f, "({}%)", self.explored)?,
+        }
+        Ok(())
+    }
+}
+
+impl Reset for ExploreQuest {
+    fn reset(&mut self) {
+        self.explored = 0;
+        self.progress.reset();
+    }
+}
+
+impl Quest<Event> for ExploreQuest {
+    #[allow(clippy::single_match)]
+    fn update(&mut self, event: &Event) {
+        if self.is_completed() {
+            return;
+        }
+        match event {
+            Event::Explore(explored) => {
+                self.explored = *explored;
+                if self.explored >= self.percent {
+                    self.progress.advance();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn status(&self) -> QuestStatus {
+        self.progress.status
+    }
+
+    fn is_completed(&self) -> bool {
+        self.progress.is_completed()
+    }
+}
+
+/// a quest going on only at a time of day, a game starts by day
+pub struct TimeQuest {
+    time: TimeOfDay,
+    now: TimeOfDay,
+    quest: Box<dyn Quest<Event>>,
+}
+
+impl TimeQuest {
+    pub fn new(time: TimeOfDay, quest: Box<dyn Quest<Event>>) -> Self {
+        Self {
+            time,
+            now: TimeOfDay::Day,
+            quest,
+        }
+    }
+}
+
+impl Display for TimeQuest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "by {}: {}", self.time, self.quest)
+    }
+}
+
+impl Reset for TimeQuest {
+    fn reset(&mut self) {
+        self.quest.reset();
+    }
+}
+
+impl Quest<Event> for TimeQuest {
+    fn update(&mut self, event: &Event) {
+        match event {
+            Event::Time(time) => {
+                self.now = *time;
+            }
+            _ if self.now == self.time => self.quest.update(event),
+            _ => {}
+        }
+    }
+
+    fn status(&self) -> QuestStatus {
+        self.quest.status()
+    }
+
+    fn is_completed(&self) -> bool {
+        self.quest.is_completed()
+    }
+}
+
+/// completed once the flag is set, e.g. by talking to someone
+pub struct FlagQuest {
+    flag: String,
+    progress: QuestProgress,
+}
+
+impl FlagQuest {
+    pub fn new(flag: String) -> Self {
+        Self {
+            flag,
+            progress: QuestProgress::new(1),
+        }
+    }
+}
+
+impl Display for FlagQuest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.", self.flag)?;
+        if self.is_completed() {
+            write!(f, "(Completed)")?;
+        }
+        Ok(())
+    }
+}
+
+impl Reset for FlagQuest {
+    fn reset(&mut self) {
+        self.progress.reset();
+    }
+}
+
+impl Quest<Event> for FlagQuest {
+    fn update(&mut self, event: &Event) {
+        match event {
+            Event::Flag(flag) if flag == &self.flag => {
+                self.progress.advance();
+            }
+            _ => {}
+        }
+    }
+
+    fn status(&self) -> QuestStatus {
+        self.progress.status
+    }
+
+    fn is_completed(&self) -> bool {
+        self.progress.is_completed()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encounter {
+    Defeat,
+    Evade,
+}
+
+pub struct EnemyQuest {
+    encounter: Encounter,
+    /// any enemy counts if None
+    name: Option<String>,
+    progress: QuestProgress,
+}
+
+impl EnemyQuest {
+    pub fn new(encounter: Encounter, name: Option<String>, number: usize) -> Self {
+        Self {
+            encounter,
+            name,
+            progress: QuestProgress::new(number),
+        }
+    }
+}
+
+impl Display for EnemyQuest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verb = match self.encounter {
+            Encounter::Defeat => "defeat",
+            Encounter::Evade => "evade",
+        };
+        let name = self.name.as_deref().unwrap_or("enemy");
+        write!(f, "{} {} {}(s).", verb, self.progress.steps, name)?;
+        match self.progress.progress() {
+            Some((a, b)) => write!(f, "({}/{})", a - 1, b)?,
+            None => write!(f, "(Completed)")?,
+        }
+        Ok(())
+    }
+}
+
+impl Reset for EnemyQuest {
+    fn reset(&mut self) {
+        self.progress.reset();
+    }
+}
+
+impl Quest<Event> for EnemyQuest {
+    fn update(&mut self, event: &Event) {
+        if self.is_completed() {
+            return;
+        }
+        let name = match (self.encounter, event) {
+            (Encounter::Defeat, Event::Defeat(name)) => name,
+            (Encounter::Evade, Event::Evade(name)) => name,
+            _ => return,
+        };
+        if self.name.as_ref().is_none_or(|n| n == name) {
+            self.progress.advance();
+        }
+    }
+
+    fn status(&self) -> QuestStatus {
+        self.progress.status
+    }
+
+    fn is_completed(&self) -> bool {
+        self.progress.is_completed()
+    }
+}
+
+/// completed once every sign of the map is read
+pub struct SignQuest {
+    signs: HashSet<Position>,
+    read: HashSet<Position>,
+    progress: QuestProgress,
+}
+
+impl SignQuest {
+    pub fn new(signs: HashSet<Position>) -> Self {
+        let progress = QuestProgress::new(signs.len());
+        Self {
+            signs,
+            read: HashSet::new(),
+            progress,
+        }
+    }
+}
+
+impl Display for SignQuest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "read every sign.")?;
+        match self.progress.progress() {
+            Some((a, b)) => write!(f, "({}/{})", a - 1, b)?,
+            None => write!(f, "(Completed)")?,
+        }
+        Ok(())
+    }
+}
+
+impl Reset for SignQuest {
+    fn reset(&mut self) {
+        self.read.clear();
+        self.progress.reset();
+    }
+}
+
+impl Quest<Event> for SignQuest {
+    fn update(&mut self, event: &Event) {
+        match event {
+            // a sign read again does not count
+            Event::Read(position) if self.signs.contains(position) && self.read.insert(*position) => {
+                self.progress.advance();
+            }
+            _ => {}
+        }
+    }
+
+    fn status(&self) -> QuestStatus {
+        self.progress.status
+    }
+
+    fn is_completed(&self) -> bool {
+        self.progress.is_completed()
+    }
+}
+
+/// completed by winning the map without dying on the way
+pub struct NoDeathQuest {
+    died: bool,
+    progress: QuestProgress,
+}
+
+impl NoDeathQuest {
+    pub fn new() -> Self {
+        Self {
+            died: false,
+            progress: QuestProgress::new(1),
+        }
+    }
+}
+
+impl Default for NoDeathQuest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for NoDeathQuest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "win without dying.")?;
+        if self.is_completed() {
+            write!(f, "(Completed)")?;
+        }
+        Ok(())
+    }
+}
+
+impl Reset for NoDeathQuest {
+    fn reset(&mut self) {
+        self.died = false;
+        self.progress.reset();
+    }
+}
+
+impl Quest<Event> for NoDeathQuest {
+    fn update(&mut self, event: &Event) {
+        match event {
+            Event::Die(_) => {
+                self.died = true;
+            }
+            Event::Win if !self.died => {
+                self.progress.advance();
+            }
+            _ => {}
+        }
+    }
+
+    fn status(&self) -> QuestStatus {
+        self.progress.status
+    }
+
+    fn is_completed(&self) -> bool {
+        self.progress.is_completed()
+    }
+}
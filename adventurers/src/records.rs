@@ -0,0 +1,160 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::stats::Statistics;
+use crate::utils::Event;
+
+/// backgrounds listed in a line of the records screen
+const WALKED_PER_LINE: usize = 4;
+const ACHIEVEMENTS_PER_LINE: usize = 3;
+/// lines of the records screen shown at once, the screen only fits so many
+const PAGE_LINES: usize = 12;
+
+/// best results of a map over all completions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MapRecord {
+    pub completions: usize,
+    /// in ticks
+    pub fastest: Option<i32>,
+    pub fewest_steps: Option<usize>,
+    pub fewest_deaths: Option<usize>,
+}
+
+/// counted over every game ever played
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Totals {
+    /// tiles walked on, by background
+    pub walked: BTreeMap<String, usize>,
+    pub items: usize,
+    pub drownings: usize,
+}
+
+/// content of the records file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Records {
+    /// by map file name
+    pub maps: BTreeMap<String, MapRecord>,
+    pub totals: Totals,
+    /// names of unlocked achievements
+    #[serde(default)]
+    pub achievements: BTreeSet<String>,
+}
+
+/// keep the smaller one, return true if the new value is kept
+fn keep_min<T: PartialOrd + Copy>(record: &mut Option<T>, value: T) -> bool {
+    if record.is_some_and(|r| r <= value) {
+        return false;
+    }
+    *record = Some(value);
+    true
+}
+
+impl Totals {
+    pub fn update(&mut self, event: &Event) {
+        match event {
+            Event::MoveTo(_, Some(background)) => {
+                *self.walked.entry(background.to_string()).or_default() += 1;
+            }
+            Event::Pickup(_) => {
+                self.items += 1;
+            }
+            Event::Drown => {
+                self.drownings += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Records {
+    /// record a completion of the map, return the names of records broken
+    pub fn complete(&mut self, map: &str, statistics: &Statistics) -> Vec<&'static str> {
+        let record = self.maps.entry(map.to_string()).or_default();
+        record.completions += 1;
+
+        let mut broken = vec![];
+        if keep_min(&mut record.fastest, statistics.ticks) {
+            broken.push("fastest");
+        }
+        if keep_min(&mut record.fewest_steps, statistics.steps) {
+            broken.push("fewest steps");
+        }
+        if keep_min(&mut record.fewest_deaths, statistics.deaths) {
+            broken.push("fewest deaths");
+        }
+        broken
+    }
+
+    /// pages of the records screen
+    pub fn pages(&self) -> usize {
+        self.lines().len().div_ceil(PAGE_LINES)
+    }
+
+    /// a page of the table shown from the title screen
+    pub fn text(&self, page: usize) -> String {
+        let lines = self.lines();
+        let pages = self.pages();
+        let page = page.min(pages - 1);
+        let mut shown = lines.chunks(PAGE_LINES).nth(page).unwrap_or_default().to_vec();
+        match pages {
+            1 => shown.push("Enter: back".into()),
+            _ => shown.push(format!("Page {} of {pages}, up/down to turn, Enter: back", page + 1)),
+        }
+        shown.join("\n")
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for (map, record) in self.maps.iter() {
+            let show = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+            lines.push(format!(
+                "{}: {} win(s), fastest {}, fewest steps {}, fewest deaths {}",
+                map,
+                record.completions,
+                show(record.fastest.map(|ticks| format!("{ticks} ticks"))),
+                show(record.fewest_steps.map(|steps| steps.to_string())),
+                show(record.fewest_deaths.map(|deaths| deaths.to_string())),
+            ));
+        }
+        if lines.is_empty() {
+            lines.push("No map completed yet".into());
+        }
+
+        let walked = self.totals.walked.iter().map(|(name, n)| format!("{name} {n}"));
+        let walked = walked.collect::<Vec<_>>();
+        if walked.is_empty() {
+            lines.push("Walked on: -".into());
+        }
+        for (i, chunk) in walked.chunks(WALKED_PER_LINE).enumerate() {
+            let head = if i == 0 { "Walked on: " } else { "  " };
+            lines.push(format!("{head}{}", chunk.join(", ")));
+        }
+        lines.push(format!(
+            "Items picked up: {}   Drownings: {}",
+            self.totals.items, self.totals.drownings
+        ));
+        let achievements = self.achievements.iter().cloned().collect::<Vec<_>>();
+        if achievements.is_empty() {
+            lines.push("Achievements: -".into());
+        }
+        for (i, chunk) in achievements.chunks(ACHIEVEMENTS_PER_LINE).enumerate() {
+            let head = if i == 0 { "Achievements: " } else { "  " };
+            lines.push(format!("{head}{}", chunk.join(", ")));
+        }
+        lines
+    }
+}
+
+pub fn read_records<P: AsRef<Path>>(path: P) -> Result<Records, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    Ok(ron::from_str::<Records>(&content)?)
+}
+
+pub fn write_records<P: AsRef<Path>>(path: P, records: &Records) -> Result<(), Box<dyn Error>> {
+    write(path, ron::to_string(records)?)?;
+    Ok(())
+}
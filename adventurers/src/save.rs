@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::enemy::Enemy;
+use crate::map::MapLayers;
+use crate::npc::Npc;
+use crate::player::Player;
+use crate::quest::{Quest, RawQuest};
+use crate::stats::Statistics;
+use crate::timer::Timer;
+use crate::trigger::Trigger;
+use crate::utils::{Event, Input, Position};
+
+const SAVES_DIR: &str = "../saves";
+
+/// a game in progress, written from the pause menu
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    /// map file the game was started from
+    pub map: PathBuf,
+    pub frame: i32,
+    pub viewport_position: Position,
+    pub player: Player,
+    pub map_layers: MapLayers,
+    pub npcs: Vec<Npc>,
+    pub enemies: Vec<Enemy>,
+    pub triggers: Vec<Trigger>,
+    pub flags: HashSet<String>,
+    /// quests with the number of events in history when they started
+    pub quests: Vec<(RawQuest, usize)>,
+    /// every event handed to quests, with the tick it was on
+    pub history: Vec<(i32, Event)>,
+    /// events not yet handed to quests
+    pub events: Vec<Event>,
+    #[serde(default)]
+    pub statistics: Statistics,
+    /// inputs of every tick of the run, with the tick they were on
+    #[serde(default)]
+    pub inputs: Vec<(i32, Input)>,
+}
+
+/// where the game on the map is saved, one save per map
+pub fn save_path<P: AsRef<Path>>(map: P) -> PathBuf {
+    let name = map.as_ref().file_name().unwrap_or_default();
+    Path::new(SAVES_DIR).join(name)
+}
+
+pub fn read_save<P: AsRef<Path>>(path: P) -> Result<SaveGame, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    Ok(ron::from_str::<SaveGame>(&content)?)
+}
+
+pub fn write_save(save: &SaveGame) -> Result<(), Box<dyn Error>> {
+    create_dir_all(SAVES_DIR)?;
+    write(save_path(&save.map), ron::to_string(save)?)?;
+    Ok(())
+}
+
+/// quests are not serializable, rebuild them by replaying the history.
+/// the timer makes the same splits again, as ticks are in the history
+pub fn replay_quests(
+    quests: &[(RawQuest, usize)],
+    history: &[(i32, Event)],
+    timer: &mut Timer,
+) -> Vec<Box<dyn Quest<Event>>> {
+    let mut replayed = quests.iter().map(|(q, _)| q.into()).collect::<Vec<Box<dyn Quest<Event>>>>();
+    for (n, (frame, event)) in history.iter().enumerate() {
+        for (index, quest) in replayed.iter_mut().enumerate() {
+            let (raw_quest, start) = &quests[index];
+            if n < *start {
+                continue;
+            }
+            let before = quest.status();
+            quest.update(event);
+            timer.update(*frame, index, raw_quest, before, quest.status());
+        }
+    }
+    replayed
+}
@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{Event, Position};
+
+/// numbers of a play through, collected from events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Statistics {
+    pub ticks: i32,
+    pub steps: usize,
+    pub deaths: usize,
+    pub items: usize,
+    /// tiles walked on
+    #[serde(alias = "explored")]
+    pub walked: HashSet<Position>,
+}
+
+impl Statistics {
+    pub fn update(&mut self, event: &Event) {
+        match event {
+            Event::MoveTo(position, _) => {
+                self.steps += 1;
+                self.walked.insert(*position);
+            }
+            Event::Pickup(_) => {
+                self.items += 1;
+            }
+            Event::Die(_) => {
+                self.deaths += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// the summary shown at the end of a game
+    pub fn summary(&self, tick_millis: u64) -> String {
+        let seconds = self.ticks as f32 * tick_millis as f32 / 1000.0;
+        format!(
+            "Time: {:.1}s ({} ticks)   Steps: {}   Deaths: {}\nItems collected: {}   Tiles walked: {}",
+            seconds,
+            self.ticks,
+            self.steps,
+            self.deaths,
+            self.items,
+            self.walked.len()
+        )
+    }
+}
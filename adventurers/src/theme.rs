@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use termgame::{GameStyle, StyledCharacter};
+
+use crate::tileset::{Colour, Tile, TileId};
+
+/// the themes file shipped with the game, also used when it is missing
+const DEFAULT_THEMES: &str = include_str!("../../themes.ron");
+
+/// glyph and colours replacing how something looks, each kept if not given
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Look {
+    #[serde(default)]
+    pub glyph: Option<char>,
+    #[serde(default)]
+    pub fg: Option<Colour>,
+    #[serde(default)]
+    pub bg: Option<Colour>,
+    /// bg of tiles out of sight at night
+    #[serde(default)]
+    pub night: Option<Colour>,
+    /// bg of tiles seen before, but out of sight now
+    #[serde(default)]
+    pub dimmed: Option<Colour>,
+}
+
+impl Look {
+    /// the tile looking this way
+    pub fn on(&self, tile: &Tile) -> Tile {
+        let (night, dimmed) = match self.bg {
+            // colours out of sight go with the bg they darken
+            Some(_) => (self.night, self.dimmed),
+            None => (self.night.or(tile.night), self.dimmed.or(tile.dimmed)),
+        };
+        Tile {
+            glyph: self.glyph.unwrap_or(tile.glyph),
+            fg: self.fg.or(tile.fg),
+            bg: self.bg.unwrap_or(tile.bg),
+            night,
+            dimmed,
+            ..tile.clone()
+        }
+    }
+
+    /// draw the character this way, over the style of the tile under it
+    pub fn apply(&self, sc: &mut StyledCharacter) {
+        if let Some(glyph) = self.glyph {
+            sc.c = glyph;
+        }
+        if self.fg.is_none() && self.bg.is_none() {
+            return;
+        }
+        let mut style = sc.style.take().unwrap_or_else(GameStyle::new);
+        if let Some(fg) = self.fg {
+            style.color = Some(fg.into());
+        }
+        if let Some(bg) = self.bg {
+            style.background_color = Some(bg.into());
+        }
+        sc.style = Some(style);
+    }
+}
+
+/// colours and glyphs of the map, picked in the options menu
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    /// looks of tiles by id, over the ones of the tileset
+    #[serde(default)]
+    pub tiles: HashMap<TileId, Look>,
+    #[serde(default)]
+    pub player: Look,
+    #[serde(default)]
+    pub sign: Look,
+    #[serde(default)]
+    pub object: Look,
+}
+
+/// the themes shipped with the game, the default one first
+pub fn builtin_themes() -> Vec<Theme> {
+    ron::from_str(DEFAULT_THEMES).expect("shipped themes are valid")
+}
+
+/// the built-in themes, replaced by the ones of the file of the same name,
+/// followed by the other ones of the file
+pub fn read_themes<P: AsRef<Path>>(path: P) -> Result<Vec<Theme>, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    let mut themes = builtin_themes();
+    for theme in ron::from_str::<Vec<Theme>>(&content)? {
+        match themes.iter_mut().find(|t| t.name == theme.name) {
+            Some(builtin) => *builtin = theme,
+            None => themes.push(theme),
+        }
+    }
+    Ok(themes)
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::Position;
+
+/// shore tiles turning from sand into water at high tide, and back at low tide
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tide {
+    pub shore: Vec<Position>,
+    /// ticks from low to high tide and back
+    pub period: i32,
+    /// ticks the tide is ahead of others, to make waves
+    #[serde(default)]
+    pub offset: i32,
+}
+
+impl Tide {
+    pub fn is_high(&self, frame: i32) -> bool {
+        (frame + self.offset).div_euclid(self.period.max(1)) % 2 == 1
+    }
+}
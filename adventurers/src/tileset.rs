@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use termgame::{GameColor, GameStyle};
+
+use crate::plain::{to_ascii, RenderMode};
+use crate::theme::Theme;
+use crate::utils::Direction;
+
+/// the tileset file shipped with the game, also used when it is missing
+const DEFAULT_TILESET: &str = include_str!("../../tileset.ron");
+
+/// tiles the game itself turns others into
+pub const ROCK: &str = "rock";
+pub const SAND: &str = "sand";
+pub const WATER: &str = "water";
+pub const BARRIER: &str = "barrier";
+pub const PLATE: &str = "plate";
+
+/// tiles placed by map objects of their own, like `Grass`
+pub const GRASS: &str = "grass";
+pub const CINDERBLOCK: &str = "cinderblock";
+pub const FLOWERBUSH: &str = "flowerbush";
+pub const ICE: &str = "ice";
+pub const BUBBLES: &str = "bubbles";
+pub const CURRENT_UP: &str = "current_up";
+pub const CURRENT_DOWN: &str = "current_down";
+pub const CURRENT_LEFT: &str = "current_left";
+pub const CURRENT_RIGHT: &str = "current_right";
+
+pub type TileId = String;
+
+/// colours a tileset can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Colour {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+    DarkGray,
+    LightRed,
+    LightGreen,
+    LightYellow,
+    LightBlue,
+    LightMagenta,
+    LightCyan,
+    White,
+    Rgb(u8, u8, u8),
+}
+
+impl From<Colour> for GameColor {
+    fn from(colour: Colour) -> Self {
+        match colour {
+            Colour::Black => GameColor::Black,
+            Colour::Red => GameColor::Red,
+            Colour::Green => GameColor::Green,
+            Colour::Yellow => GameColor::Yellow,
+            Colour::Blue => GameColor::Blue,
+            Colour::Magenta => GameColor::Magenta,
+            Colour::Cyan => GameColor::Cyan,
+            Colour::Gray => GameColor::Gray,
+            Colour::DarkGray => GameColor::DarkGray,
+            Colour::LightRed => GameColor::LightRed,
+            Colour::LightGreen => GameColor::LightGreen,
+            Colour::LightYellow => GameColor::LightYellow,
+            Colour::LightBlue => GameColor::LightBlue,
+            Colour::LightMagenta => GameColor::LightMagenta,
+            Colour::LightCyan => GameColor::LightCyan,
+            Colour::White => GameColor::White,
+            Colour::Rgb(r, g, b) => GameColor::Rgb(r, g, b),
+        }
+    }
+}
+
+fn default_glyph() -> char {
+    ' '
+}
+
+fn default_walkable() -> bool {
+    true
+}
+
+fn default_cost() -> f32 {
+    1.0
+}
+
+/// a kind of background tile in the tileset file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tile {
+    pub name: String,
+    /// drawn on the tile under what stands there
+    #[serde(default = "default_glyph")]
+    pub glyph: char,
+    /// drawn instead of the glyph without unicode or colours
+    #[serde(default)]
+    pub ascii: Option<char>,
+    #[serde(default)]
+    pub fg: Option<Colour>,
+    pub bg: Colour,
+    /// bg out of sight at night
+    #[serde(default)]
+    pub night: Option<Colour>,
+    /// bg of a tile seen before, but out of sight now
+    #[serde(default)]
+    pub dimmed: Option<Colour>,
+    #[serde(default = "default_walkable")]
+    pub walkable: bool,
+    /// swum in, the player drowns without oxygen
+    #[serde(default)]
+    pub water: bool,
+    /// fills the oxygen up to capacity
+    #[serde(default)]
+    pub air: bool,
+    /// the player slides over it until stopped
+    #[serde(default)]
+    pub slide: bool,
+    /// pushes the player a tile in the direction every tick
+    #[serde(default)]
+    pub current: Option<Direction>,
+    /// how many tiles of plain ground it takes to walk onto this
+    #[serde(default = "default_cost")]
+    pub cost: f32,
+}
+
+impl Tile {
+    /// glyph drawn in the mode, plain ones tell tiles apart by it
+    pub fn glyph_in(&self, mode: RenderMode) -> char {
+        match mode.is_plain() {
+            true => self.ascii.unwrap_or(to_ascii(self.glyph)),
+            false => self.glyph,
+        }
+    }
+    fn style_with(&self, bg: Colour) -> GameStyle {
+        GameStyle::new()
+            .color(self.fg.map(GameColor::from))
+            .background_color(Some(bg.into()))
+    }
+    pub fn style(&self) -> GameStyle {
+        self.style_with(self.bg)
+    }
+    /// style out of the light, darker colours at night
+    pub fn night_style(&self) -> GameStyle {
+        self.style_with(self.night.unwrap_or(self.bg))
+    }
+    /// style of a tile seen before, but out of sight now
+    pub fn dimmed_style(&self) -> GameStyle {
+        self.style_with(self.dimmed.unwrap_or(self.bg))
+    }
+}
+
+/// tiles by id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Tileset(HashMap<TileId, Tile>);
+
+impl Tileset {
+    /// the tiles shipped with the game
+    pub fn builtin() -> Self {
+        ron::from_str(DEFAULT_TILESET).expect("shipped tileset is valid")
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Tile> {
+        self.0.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.contains_key(id)
+    }
+
+    /// the tiles looking the way the theme has them
+    pub fn themed(&self, theme: &Theme) -> Tileset {
+        let tiles = self.0.iter().map(|(id, tile)| match theme.tiles.get(id) {
+            Some(look) => (id.clone(), look.on(tile)),
+            None => (id.clone(), tile.clone()),
+        });
+        Tileset(tiles.collect())
+    }
+
+    /// these tiles with the other ones added, replacing the ones of the same id
+    pub fn with(&self, other: &Tileset) -> Tileset {
+        let mut tiles = self.0.clone();
+        tiles.extend(other.0.clone());
+        Tileset(tiles)
+    }
+}
+
+/// the built-in tiles with the ones of the file added
+pub fn read_tileset<P: AsRef<Path>>(path: P) -> Result<Tileset, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    let tileset = ron::from_str::<Tileset>(&content)?;
+    Ok(Tileset::builtin().with(&tileset))
+}
@@ -0,0 +1,129 @@
+use std::error::Error;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::quest::{QuestStatus, RawQuest};
+use crate::utils::Input;
+
+const SPLITS_DIR: &str = "../splits";
+/// splits shown in the timer, the latest ones
+const SHOWN_SPLITS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Split {
+    pub name: String,
+    /// tick the split happened on
+    pub frame: i32,
+}
+
+/// speedrun timer counting ticks, split at quest stages
+#[derive(Default)]
+pub struct Timer {
+    pub splits: Vec<Split>,
+    /// splits of the personal best run
+    pub best: Vec<Split>,
+}
+
+impl Timer {
+    /// split if a compound quest went on a stage or a quest is completed,
+    /// return true if it did
+    pub fn update(
+        &mut self,
+        frame: i32,
+        index: usize,
+        raw_quest: &RawQuest,
+        before: QuestStatus,
+        after: QuestStatus,
+    ) -> bool {
+        let Some(stage) = raw_quest.milestone(before, after) else {
+            return false;
+        };
+        let name = match raw_quest {
+            RawQuest::Compound(_) => format!("quest {} stage {}", index + 1, stage),
+            _ => format!("quest {}", index + 1),
+        };
+        self.splits.push(Split { name, frame });
+        true
+    }
+
+    pub fn finish(&mut self, frame: i32) {
+        self.splits.push(Split {
+            name: "finish".into(),
+            frame,
+        });
+    }
+
+    /// ticks ahead of (negative) or behind the best run at the nth split
+    pub fn delta(&self, n: usize) -> Option<i32> {
+        let split = self.splits.get(n)?;
+        let best = self.best.get(n)?;
+        Some(split.frame - best.frame)
+    }
+
+    /// delta of the nth split as shown to the player
+    pub fn delta_text(&self, n: usize) -> String {
+        match self.delta(n) {
+            Some(delta) if delta < 0 => format!("{} ahead", -delta),
+            Some(delta) if delta > 0 => format!("{delta} behind"),
+            Some(_) => "even".into(),
+            None => "no best yet".into(),
+        }
+    }
+
+    pub fn split_text(&self, n: usize) -> String {
+        let split = &self.splits[n];
+        format!("{}: {} ticks, {}", split.name, split.frame, self.delta_text(n))
+    }
+
+    /// the finished run beats the best one
+    pub fn is_best(&self) -> bool {
+        match (self.splits.last(), self.best.last()) {
+            (Some(split), Some(best)) => split.frame < best.frame,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// current time and the latest splits with their deltas
+    pub fn text(&self, frame: i32, tick_millis: u64) -> String {
+        let seconds = |frame: i32| frame as f32 * tick_millis as f32 / 1000.0;
+        let mut lines = vec![format!("{} ticks ({:.1}s)", frame, seconds(frame))];
+        let shown = self.splits.len().saturating_sub(SHOWN_SPLITS)..self.splits.len();
+        lines.extend(shown.map(|n| self.split_text(n)));
+        lines.join("\n")
+    }
+}
+
+/// where the personal best splits of the map are kept
+pub fn splits_path<P: AsRef<Path>>(map: P) -> PathBuf {
+    let name = map.as_ref().file_name().unwrap_or_default();
+    Path::new(SPLITS_DIR).join(name)
+}
+
+/// where the inputs of the personal best run of the map are kept, beside its splits
+pub fn inputs_path<P: AsRef<Path>>(map: P) -> PathBuf {
+    let name = map.as_ref().file_stem().unwrap_or_default().to_string_lossy();
+    Path::new(SPLITS_DIR).join(format!("{name}.inputs.ron"))
+}
+
+pub fn write_inputs<P>(path: P, inputs: &[(i32, Input)]) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    create_dir_all(SPLITS_DIR)?;
+    write(path, ron::to_string(inputs)?)?;
+    Ok(())
+}
+
+pub fn read_splits<P: AsRef<Path>>(path: P) -> Result<Vec<Split>, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    Ok(ron::from_str::<Vec<Split>>(&content)?)
+}
+
+pub fn write_splits<P: AsRef<Path>>(path: P, splits: &[Split]) -> Result<(), Box<dyn Error>> {
+    create_dir_all(SPLITS_DIR)?;
+    write(path, ron::to_string(splits)?)?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::map::MapLayers;
+use crate::notify::Notifications;
+use crate::utils::{Event, ForegroundVariant, Item, MessageType, Position};
+
+/// what sets a trigger off
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerInput {
+    /// lever with given id is pulled into the given state
+    Lever(char, bool),
+    /// every plate of the puzzle has a boulder on
+    Plate(char),
+    /// player reads the sign at position
+    Sign(Position),
+    Pickup(Item),
+    /// the nth quest is completed
+    Quest(usize),
+}
+
+/// what a trigger does to the map
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerOutput {
+    /// raise barriers on the tiles, or lower them if they are barriers
+    ToggleBarrier(Vec<Position>),
+    Flood(Vec<Position>),
+    Drain(Vec<Position>),
+    Spawn(Position, Item),
+    Message(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub input: TriggerInput,
+    pub outputs: Vec<TriggerOutput>,
+    /// fire only the first time the input happens
+    #[serde(default)]
+    pub once: bool,
+    #[serde(default)]
+    pub fired: bool,
+}
+
+impl TriggerInput {
+    pub fn is_fired_by(&self, event: &Event) -> bool {
+        use TriggerInput::*;
+        match (self, event) {
+            (Lever(id, on), Event::PullLever(lever, state)) => id == lever && on == state,
+            (Plate(id), Event::PuzzleSolved(puzzle)) => id == puzzle,
+            (Sign(position), Event::Read(sign)) => position == sign,
+            (Pickup(item), Event::Pickup(picked)) => item == picked,
+            (Quest(index), Event::QuestCompleted(completed)) => index == completed,
+            _ => false,
+        }
+    }
+}
+
+impl TriggerOutput {
+    pub fn apply(&self, map_layers: &mut MapLayers, notifications: &mut Notifications) {
+        use TriggerOutput::*;
+        match self {
+            ToggleBarrier(positions) => {
+                for position in positions {
+                    map_layers.toggle_barrier(position);
+                }
+            }
+            Flood(positions) => {
+                for position in positions {
+                    map_layers.flood(position);
+                }
+            }
+            Drain(positions) => {
+                for position in positions {
+                    map_layers.drain(position);
+                }
+            }
+            Spawn(position, item) => {
+                map_layers
+                    .foregrounds
+                    .insert(*position, ForegroundVariant::Object(*item));
+                map_layers.should_draw.push(*position);
+            }
+            Message(s) => {
+                notifications.push(MessageType::Notice(s.clone()));
+            }
+        }
+    }
+}
+
+impl Trigger {
+    /// apply outputs if the event sets the trigger off
+    pub fn update(
+        &mut self,
+        event: &Event,
+        map_layers: &mut MapLayers,
+        notifications: &mut Notifications,
+    ) {
+        if self.once && self.fired {
+            return;
+        }
+        if !self.input.is_fired_by(event) {
+            return;
+        }
+        for output in self.outputs.iter() {
+            output.apply(map_layers, notifications);
+        }
+        self.fired = true;
+    }
+}
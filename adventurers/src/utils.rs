@@ -1,11 +1,18 @@
-use std::{ops::{Add, AddAssign}, fmt::Display};
+use std::ops::{Add, AddAssign};
+use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
-use termgame::{GameColor, GameStyle, KeyCode, Message, ViewportLocation};
+use termgame::{Message, ViewportLocation};
+
+use crate::bindings::Action;
+use crate::clock::TimeOfDay;
+use crate::movement::MovementRule;
+use crate::tileset::{self, TileId};
 
 #[derive(Copy, Clone, Default, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct Position(pub i32, pub i32);
 
+#[allow(clippy::from_over_into)]
 impl Into<ViewportLocation> for Position {
     fn into(self) -> ViewportLocation {
         ViewportLocation {
@@ -42,52 +49,151 @@ impl AddAssign<&Position> for Position {
     }
 }
 
+/// without key release events, a key is released if not repeated in time
+const HOLD_TIMEOUT: Duration = Duration::from_millis(150);
+/// without key release events, a key pressed again within this is repeated by the terminal,
+/// so held; taps, even quick ones, come further apart
+const REPEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Default, Clone, Copy)]
+pub struct Held {
+    /// pressed since last tick, so a quick tap still counts
+    pub tapped: bool,
+    /// last time the key was pressed while held, None once released
+    pub since: Option<Instant>,
+    pub last_press: Option<Instant>,
+}
+
+impl Held {
+    pub fn is_active(&self) -> bool {
+        self.tapped || self.since.is_some()
+    }
+}
+
 #[derive(Default)]
 pub struct Control {
-    pub up: bool,
-    pub down: bool,
-    pub left: bool,
-    pub right: bool,
+    pub up: Held,
+    pub down: Held,
+    pub left: Held,
+    pub right: Held,
+    /// terminal reports key releases, so held keys never time out
+    pub reports_release: bool,
 }
 
 impl Control {
-    pub fn clear(&mut self) {
-        *self = Self::default();
+    /// keys held for the action, two for diagonal moves
+    fn held_mut(&mut self, action: Action) -> Vec<&mut Held> {
+        use Action::*;
+        let Control {
+            up,
+            down,
+            left,
+            right,
+            ..
+        } = self;
+        match action {
+            MoveLeft => vec![left],
+            MoveRight => vec![right],
+            MoveUp => vec![up],
+            MoveDown => vec![down],
+            MoveUpLeft => vec![up, left],
+            MoveUpRight => vec![up, right],
+            MoveDownLeft => vec![down, left],
+            MoveDownRight => vec![down, right],
+            _ => vec![],
+        }
     }
 
-    pub fn update(&mut self, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Left => {
-                self.left = true;
-            }
-            KeyCode::Right => {
-                self.right = true;
+    /// forget taps of this tick and keys that are no longer held
+    pub fn clear(&mut self) {
+        let reports_release = self.reports_release;
+        for held in [&mut self.up, &mut self.down, &mut self.left, &mut self.right] {
+            held.tapped = false;
+            if reports_release {
+                continue;
             }
-            KeyCode::Up => {
-                self.up = true;
+            if held.since.is_some_and(|since| since.elapsed() > HOLD_TIMEOUT) {
+                held.since = None;
             }
-            KeyCode::Down => {
-                self.down = true;
+        }
+    }
+
+    pub fn update(&mut self, action: Action) {
+        let reports_release = self.reports_release;
+        for held in self.held_mut(action) {
+            let now = Instant::now();
+            // a single press is a tap, until the terminal starts repeating it
+            let repeated = held.last_press.is_some_and(|last| now - last < REPEAT_INTERVAL);
+            if reports_release || repeated {
+                held.since = Some(now);
             }
-            _ => {}
+            held.tapped = true;
+            held.last_press = Some(now);
+        }
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.reports_release = true;
+        for held in self.held_mut(action) {
+            held.since = None;
+        }
+    }
+}
+
+/// what the player did on a tick, kept with the run so it can be simulated again
+/// tick by tick, without the timing of the keys
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    /// step wanted from held keys or auto walk, before the movement rule
+    Move(Position),
+    Undo,
+    /// next page of the dialogue, or its end
+    NextPage,
+    /// the nth choice offered on the last page of the dialogue
+    Choose(usize),
+    LeaveDialogue,
+    /// map started again after death
+    Restart,
+    /// movement rule changed in the options
+    Movement(MovementRule),
+    /// walking speed changed in the options
+    Speed(f32),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl From<Direction> for Position {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Up => Position(0, -1),
+            Direction::Down => Position(0, 1),
+            Direction::Left => Position(-1, 0),
+            Direction::Right => Position(1, 0),
         }
     }
 }
 
 impl From<&Control> for Position {
+    #[allow(clippy::needless_return)]
     fn from(control: &Control) -> Self {
         let mut x = 0;
         let mut y = 0;
-        if control.left {
+        if control.left.is_active() {
             x -= 1;
         }
-        if control.right {
+        if control.right.is_active() {
             x += 1;
         }
-        if control.up {
+        if control.up.is_active() {
             y -= 1;
         }
-        if control.down {
+        if control.down.is_active() {
             y += 1;
         }
         return Position(x, y);
@@ -100,26 +206,71 @@ pub enum MessageType {
     Sign(String),
     Debug(String),
     Pickup(char),
+    Locked(Item),
+    Unlock(Item),
+    Puzzle(char),
+    Notice(String),
+    /// speaker name and text
+    Dialogue(String, String),
+    /// enemy name and health left
+    Hurt(String, i32),
+    Defeat(String),
+    Walk(String),
+    Help(String),
     Bag(String),
     Quest(String),
+    /// speedrun timer and its splits
+    Timer(String),
+    Split(String),
+    /// name of the equipment put on
+    Equip(String),
+    /// a quest changed its status
+    Progress(String),
+    Log(String),
+    /// name and description of an achievement just unlocked
+    Achievement(String, String),
+    /// title and items of a menu
+    Menu(String, String),
+    Victory(String),
     #[default]
     None,
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Option<(String, String)>> for MessageType {
     fn into(self) -> Option<(String, String)> {
         Some(match self {
             MessageType::Sign(s) => ("You saw a message on the sign".into(), s),
             MessageType::Death(s) => ("You died".into(), s),
             MessageType::Pickup(c) => ("Pick up an object".into(), format!("You pick up '{c}'")),
+            MessageType::Locked(c) => ("Door is locked".into(), format!("You need '{c}' to open it")),
+            MessageType::Unlock(c) => ("Open the door".into(), format!("You open it with '{c}'")),
+            MessageType::Puzzle(c) => ("Puzzle solved".into(), format!("Plates '{c}' all pressed")),
+            MessageType::Notice(s) => ("Something happened".into(), s),
+            MessageType::Dialogue(name, s) => (name, s),
+            MessageType::Hurt(name, health) => {
+                ("Ouch".into(), format!("{name} hurts you, {health} health left"))
+            }
+            MessageType::Defeat(name) => ("Victory".into(), format!("You defeat {name}")),
+            MessageType::Walk(s) => ("Walk".into(), s),
+            MessageType::Help(s) => ("Keys".into(), s),
             MessageType::Bag(s) => ("Your bag has".into(), s),
             MessageType::Quest(s) => ("Quest".into(), s),
+            MessageType::Timer(s) => ("Timer".into(), s),
+            MessageType::Split(s) => ("Split".into(), s),
+            MessageType::Progress(s) => ("Quest progress".into(), s),
+            MessageType::Equip(name) => ("Equipment".into(), format!("You put on the {name}")),
+            MessageType::Log(s) => ("Message log".into(), s),
+            MessageType::Achievement(name, s) => (format!("Achievement: {name}"), s),
             MessageType::Debug(s) => ("Debug".into(), s),
+            MessageType::Menu(title, s) => (title, s),
+            MessageType::Victory(s) => ("You won".into(), s),
             MessageType::None => return None,
         })
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Option<Message>> for MessageType {
     fn into(self) -> Option<Message> {
         if let Some((title, text)) = self.into() {
@@ -139,105 +290,93 @@ pub enum RawMapObject {
     Flowerbush,
     Barrier,
     Water,
+    Plate(char),
+    Ice,
+    Current(Direction),
+    Bubbles,
+    /// a tile of the tileset by id
+    Tile(TileId),
     Sign(String),
     Object(char),
+    Boulder,
+    Lever(char),
+    Door {
+        key: Item,
+        #[serde(default)]
+        consume: bool,
+    },
 }
 
 pub enum MapObjectVariant {
     Foreground(ForegroundVariant),
-    Background(BackgroundVariant),
+    Background(TileId),
+    /// pressure plate of the puzzle with given id
+    Plate(char),
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<MapObjectVariant> for &RawMapObject {
     fn into(self) -> MapObjectVariant {
-        use BackgroundVariant as B;
         use ForegroundVariant as F;
+        use MapObjectVariant::Background as B;
         use RawMapObject::*;
         match self {
             Object(c) => F::Object(*c).into(),
+            Boulder => F::Boulder.into(),
+            Lever(id) => F::Lever { id: *id, on: false }.into(),
             Sign(s) => F::Sign(s.clone()).into(),
+            Door { key, consume } => F::Door {
+                key: *key,
+                consume: *consume,
+            }
+            .into(),
 
-            Barrier => B::Barrier.into(),
-            Cinderblock => B::Cinderblock.into(),
-            Flowerbush => B::Flowerbush.into(),
-            Grass => B::Grass.into(),
-            Rock => B::Rock.into(),
-            Sand => B::Sand.into(),
-            Water => B::Water.into(),
+            Tile(id) => B(id.clone()),
+            Plate(c) => MapObjectVariant::Plate(*c),
+            Barrier => B(tileset::BARRIER.into()),
+            Cinderblock => B(tileset::CINDERBLOCK.into()),
+            Flowerbush => B(tileset::FLOWERBUSH.into()),
+            Grass => B(tileset::GRASS.into()),
+            Rock => B(tileset::ROCK.into()),
+            Sand => B(tileset::SAND.into()),
+            Water => B(tileset::WATER.into()),
+            Ice => B(tileset::ICE.into()),
+            Current(Direction::Up) => B(tileset::CURRENT_UP.into()),
+            Current(Direction::Down) => B(tileset::CURRENT_DOWN.into()),
+            Current(Direction::Left) => B(tileset::CURRENT_LEFT.into()),
+            Current(Direction::Right) => B(tileset::CURRENT_RIGHT.into()),
+            Bubbles => B(tileset::BUBBLES.into()),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum BackgroundVariant {
-    Grass,
-    Sand,
-    Rock,
-    Cinderblock,
-    Flowerbush,
-    Barrier,
-    Water,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ForegroundVariant {
     Sign(String),
     Object(char),
+    /// can be pushed by the player, fills water it is pushed into
+    Boulder,
+    /// pulled by walking into it
+    Lever { id: char, on: bool },
+    /// blocks the way until the player brings the key
+    Door { key: Item, consume: bool },
 }
 
-impl Into<MapObjectVariant> for ForegroundVariant {
-    fn into(self) -> MapObjectVariant {
-        MapObjectVariant::Foreground(self)
-    }
-}
-
-impl BackgroundVariant {
+impl ForegroundVariant {
     pub fn is_barrier(&self) -> bool {
-        self == &BackgroundVariant::Barrier
-    }
-    pub fn is_water(&self) -> bool {
-        self == &BackgroundVariant::Water
+        matches!(
+            self,
+            ForegroundVariant::Door { .. }
+                | ForegroundVariant::Boulder
+                | ForegroundVariant::Lever { .. }
+        )
     }
 }
 
-impl Into<MapObjectVariant> for BackgroundVariant {
+#[allow(clippy::from_over_into)]
+impl Into<MapObjectVariant> for ForegroundVariant {
     fn into(self) -> MapObjectVariant {
-        MapObjectVariant::Background(self)
-    }
-}
-
-impl Into<Option<GameColor>> for &BackgroundVariant {
-    fn into(self) -> Option<GameColor> {
-        use BackgroundVariant::*;
-        Some(match self {
-            Grass => GameColor::Green,
-            Sand => GameColor::LightYellow,
-            Rock => GameColor::DarkGray,
-            Cinderblock => GameColor::LightRed,
-            Flowerbush => GameColor::LightMagenta,
-            Barrier => GameColor::Black,
-            Water => GameColor::LightBlue,
-        })
-    }
-}
-
-impl Into<GameStyle> for &BackgroundVariant {
-    fn into(self) -> GameStyle {
-        GameStyle::new().background_color(self.into())
-    }
-}
-
-impl Display for BackgroundVariant {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", match self {
-            BackgroundVariant::Grass => "grass",
-            BackgroundVariant::Sand => "sand",
-            BackgroundVariant::Rock => "rock",
-            BackgroundVariant::Cinderblock => "cinderblock",
-            BackgroundVariant::Flowerbush => "flowerbush",
-            BackgroundVariant::Barrier => "barrier",
-            BackgroundVariant::Water => "water",
-        })
+        MapObjectVariant::Foreground(self)
     }
 }
 
@@ -248,12 +387,37 @@ impl Display for BackgroundVariant {
 
 pub type Item = char;
 
-#[derive(Clone)]
+/// cheap deterministic noise, good enough for wandering around
+pub fn pseudo_random(seed: u64) -> u64 {
+    let mut x = seed.wrapping_add(0x9E3779B97F4A7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+    x ^ (x >> 31)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Event {
-    MoveTo(Position, Option<BackgroundVariant>),
+    MoveTo(Position, Option<TileId>),
     Pickup(Item),
+    Read(Position),
+    PullLever(char, bool),
+    PuzzleSolved(char),
+    QuestCompleted(usize),
+    Flag(String),
+    /// an enemy is defeated, by name
+    Defeat(String),
+    /// an enemy lost sight of the player, by name
+    Evade(String),
     // Interact(Interactable),
     Die(String),
+    /// player ran out of oxygen
+    Drown,
+    /// percent of the map seen so far, once it grows
+    Explore(usize),
+    /// day or night began
+    Time(TimeOfDay),
+    /// the win conditions of the map hold
+    Win,
 }
 
 
@@ -40,7 +40,7 @@ impl QuestProgress {
     }
 
     /// return true if progress went to next step
-    pub fn next(&mut self) -> bool {
+    pub fn advance(&mut self) -> bool {
         use QuestStatus::*;
         match &mut self.status {
             Pending(step) => {